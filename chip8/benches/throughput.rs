@@ -0,0 +1,49 @@
+// Run with `cargo bench -p chip8`. Every machine uses a constant rng so runs
+// are comparable
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use chip8::Chip8;
+
+fn machine(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.set_rng_constant(Some(0xA5));
+    chip8.load_rom(rom).unwrap();
+    chip8
+}
+
+fn frame(c: &mut Criterion) {
+    let mut chip8 = machine(include_bytes!("../../programs/IBM Logo.ch8"));
+    c.bench_function("frame ibm logo", |b| b.iter(|| chip8.frame().unwrap()));
+}
+
+fn opcodes(c: &mut Criterion) {
+    let mut chip8 = machine(&[]);
+    chip8.i = 0x300;
+    chip8.registers[0] = 13;
+    chip8.registers[1] = 7;
+    c.bench_function("Dxyn unaligned 8x15", |b| {
+        b.iter(|| chip8.step(black_box(0xD0), black_box(0x1F)).unwrap())
+    });
+
+    let mut chip8 = machine(&[]);
+    chip8.i = 0x300;
+    c.bench_function("Fx55 all registers", |b| {
+        b.iter(|| chip8.step(black_box(0xFF), black_box(0x55)).unwrap())
+    });
+}
+
+fn full_rom(c: &mut Criterion) {
+    let rom = include_bytes!("../../programs/3-corax+.ch8");
+    c.bench_function("corax+ 600 frames", |b| {
+        b.iter(|| {
+            let mut chip8 = machine(rom);
+            chip8.run_frames(600).unwrap();
+            chip8.display_hash()
+        })
+    });
+}
+
+criterion_group!(benches, frame, opcodes, full_rom);
+criterion_main!(benches);
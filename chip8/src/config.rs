@@ -0,0 +1,47 @@
+use crate::{InvalidOpPolicy, Quirks, ReservedWritePolicy, DEFAULT_MAX_STACK_DEPTH, FRAME_DURATION};
+
+// Everything that can be set up front when creating a machine, the setters
+// on Chip8 remain available to change these at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chip8Config {
+    pub quirks: Quirks,
+    // Cycle budget of a frame, raising it speeds up emulation
+    pub cycles_per_frame: usize,
+    // Runs exactly this many instructions a frame whatever they cost instead
+    // of spending cycles_per_frame, like most web emulators
+    pub instructions_per_frame: Option<usize>,
+    // Dxyn reads two bytes a row and draws 16 pixels wide sprites, whatever
+    // the mode. An experimental extension, no interpreter does this
+    pub wide_sprites: bool,
+    // 5xy1 and the like are invalid ops instead of running as 5xy0
+    pub strict_opcodes: bool,
+    // XO-CHIP extensions, see Chip8::set_xochip
+    pub xochip: bool,
+    pub max_stack_depth: Option<usize>,
+    pub invalid_op_policy: InvalidOpPolicy,
+    pub reserved_write_policy: ReservedWritePolicy,
+    pub tone_hz: Option<u16>,
+    // Seeds the rng from entropy when None
+    pub seed: Option<u64>,
+    // Leaves 0x000-0x0F0 zeroed instead of holding the built-in fonts
+    pub load_default_font: bool,
+}
+
+impl Default for Chip8Config {
+    fn default() -> Self {
+        return Self {
+            quirks: Quirks::default(),
+            cycles_per_frame: FRAME_DURATION as usize,
+            instructions_per_frame: None,
+            wide_sprites: false,
+            strict_opcodes: false,
+            xochip: false,
+            max_stack_depth: Some(DEFAULT_MAX_STACK_DEPTH),
+            invalid_op_policy: InvalidOpPolicy::default(),
+            reserved_write_policy: ReservedWritePolicy::default(),
+            tone_hz: None,
+            seed: None,
+            load_default_font: true,
+        };
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+
+use crate::Instruction;
+
+// Cowgod-style mnemonics
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match *self {
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::ScrollDown(n) => write!(f, "SCD {}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Low => write!(f, "LOW"),
+            Instruction::High => write!(f, "HIGH"),
+            Instruction::Jp(addr) => write!(f, "JP 0x{:03X}", addr),
+            Instruction::Call(addr) => write!(f, "CALL 0x{:03X}", addr),
+            Instruction::Se(x, byte) => write!(f, "SE V{:X}, 0x{:02X}", x, byte),
+            Instruction::Sne(x, byte) => write!(f, "SNE V{:X}, 0x{:02X}", x, byte),
+            Instruction::SeXy(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::Ld(x, byte) => write!(f, "LD V{:X}, 0x{:02X}", x, byte),
+            Instruction::Add(x, byte) => write!(f, "ADD V{:X}, 0x{:02X}", x, byte),
+            Instruction::LdXy(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::OrXy(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::AndXy(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::XorXy(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddXy(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::SubXy(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShrXy(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubnXy(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShlXy(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SneXy(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI(addr) => write!(f, "LD I, 0x{:03X}", addr),
+            Instruction::JpV0(addr) => write!(f, "JP V0, 0x{:03X}", addr),
+            Instruction::Rnd(x, byte) => write!(f, "RND V{:X}, 0x{:02X}", x, byte),
+            Instruction::Drw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp(x) => write!(f, "SKP V{:X}", x),
+            Instruction::Sknp(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LdXDt(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::LdXK(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::LdDtX(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::LdStX(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIX(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LdILong => write!(f, "LD I, LONG"),
+            Instruction::Plane(n) => write!(f, "PLANE {}", n),
+            Instruction::Audio => write!(f, "AUDIO"),
+            Instruction::LdFX(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LdHfX(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::LdBX(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::PitchX(x) => write!(f, "PITCH V{:X}", x),
+            Instruction::LdIX(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::LdXI(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::LdRX(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::LdXR(x) => write!(f, "LD V{:X}, R", x),
+        };
+    }
+}
+
+// Unknown opcodes are shown as raw data words
+pub fn disassemble(op0: u8, op1: u8) -> String {
+    return match Instruction::decode(op0, op1) {
+        Some(instruction) => instruction.to_string(),
+        None => format!("DW 0x{:02X}{:02X}", op0, op1),
+    };
+}
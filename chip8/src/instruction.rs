@@ -0,0 +1,165 @@
+// A decoded opcode, registers are given by index and addresses/bytes as is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // 00e0
+    Cls,
+    // 00ee
+    Ret,
+    // 00cn
+    ScrollDown(u8),
+    // 00fb
+    ScrollRight,
+    // 00fc
+    ScrollLeft,
+    // 00fd
+    Exit,
+    // 00fe
+    Low,
+    // 00ff
+    High,
+    // 1nnn
+    Jp(u16),
+    // 2nnn
+    Call(u16),
+    // 3xnn
+    Se(u8, u8),
+    // 4xnn
+    Sne(u8, u8),
+    // 5xy0
+    SeXy(u8, u8),
+    // 6xnn
+    Ld(u8, u8),
+    // 7xnn
+    Add(u8, u8),
+    // 8xy0
+    LdXy(u8, u8),
+    // 8xy1
+    OrXy(u8, u8),
+    // 8xy2
+    AndXy(u8, u8),
+    // 8xy3
+    XorXy(u8, u8),
+    // 8xy4
+    AddXy(u8, u8),
+    // 8xy5
+    SubXy(u8, u8),
+    // 8xy6
+    ShrXy(u8, u8),
+    // 8xy7
+    SubnXy(u8, u8),
+    // 8xyE
+    ShlXy(u8, u8),
+    // 9xy0
+    SneXy(u8, u8),
+    // Annn
+    LdI(u16),
+    // Bnnn
+    JpV0(u16),
+    // Cxkk
+    Rnd(u8, u8),
+    // Dxyn
+    Drw(u8, u8, u8),
+    // Ex9E
+    Skp(u8),
+    // ExA1
+    Sknp(u8),
+    // F000 nnnn, XO-CHIP. nnnn is the next two bytes
+    LdILong,
+    // Fn01, XO-CHIP
+    Plane(u8),
+    // F002, XO-CHIP
+    Audio,
+    // Fx07
+    LdXDt(u8),
+    // Fx0A
+    LdXK(u8),
+    // Fx15
+    LdDtX(u8),
+    // Fx18
+    LdStX(u8),
+    // Fx1E
+    AddIX(u8),
+    // Fx29
+    LdFX(u8),
+    // Fx30
+    LdHfX(u8),
+    // Fx33
+    LdBX(u8),
+    // Fx3A, XO-CHIP
+    PitchX(u8),
+    // Fx55
+    LdIX(u8),
+    // Fx65
+    LdXI(u8),
+    // Fx75
+    LdRX(u8),
+    // Fx85
+    LdXR(u8),
+}
+
+impl Instruction {
+    pub fn decode(op0: u8, op1: u8) -> Option<Self> {
+        return Some(match op0 & 0xf0 {
+            0x00 => match op1 {
+                0xe0 => Self::Cls,
+                0xee => Self::Ret,
+                0xc0..=0xcf => Self::ScrollDown(lo!(op1)),
+                0xfb => Self::ScrollRight,
+                0xfc => Self::ScrollLeft,
+                0xfd => Self::Exit,
+                0xfe => Self::Low,
+                0xff => Self::High,
+                _ => return None,
+            },
+            0x10 => Self::Jp(nnn!(op0, op1)),
+            0x20 => Self::Call(nnn!(op0, op1)),
+            0x30 => Self::Se(lo!(op0), op1),
+            0x40 => Self::Sne(lo!(op0), op1),
+            0x50 => Self::SeXy(lo!(op0), hi!(op1)),
+            0x60 => Self::Ld(lo!(op0), op1),
+            0x70 => Self::Add(lo!(op0), op1),
+            0x80 => match op1 & 0x0f {
+                0x00 => Self::LdXy(lo!(op0), hi!(op1)),
+                0x01 => Self::OrXy(lo!(op0), hi!(op1)),
+                0x02 => Self::AndXy(lo!(op0), hi!(op1)),
+                0x03 => Self::XorXy(lo!(op0), hi!(op1)),
+                0x04 => Self::AddXy(lo!(op0), hi!(op1)),
+                0x05 => Self::SubXy(lo!(op0), hi!(op1)),
+                0x06 => Self::ShrXy(lo!(op0), hi!(op1)),
+                0x07 => Self::SubnXy(lo!(op0), hi!(op1)),
+                0x0E => Self::ShlXy(lo!(op0), hi!(op1)),
+                _ => return None,
+            },
+            0x90 => Self::SneXy(lo!(op0), hi!(op1)),
+            0xA0 => Self::LdI(nnn!(op0, op1)),
+            0xB0 => Self::JpV0(nnn!(op0, op1)),
+            0xC0 => Self::Rnd(lo!(op0), op1),
+            0xD0 => Self::Drw(lo!(op0), hi!(op1), lo!(op1)),
+            0xE0 => match op1 {
+                0x9E => Self::Skp(lo!(op0)),
+                0xA1 => Self::Sknp(lo!(op0)),
+                _ => return None,
+            },
+            0xF0 => match op1 {
+                0x00 if lo!(op0) == 0 => Self::LdILong,
+                0x01 => Self::Plane(lo!(op0)),
+                0x02 if lo!(op0) == 0 => Self::Audio,
+                0x07 => Self::LdXDt(lo!(op0)),
+                0x0A => Self::LdXK(lo!(op0)),
+                0x15 => Self::LdDtX(lo!(op0)),
+                0x18 => Self::LdStX(lo!(op0)),
+                0x1E => Self::AddIX(lo!(op0)),
+                0x29 => Self::LdFX(lo!(op0)),
+                0x30 => Self::LdHfX(lo!(op0)),
+                0x33 => Self::LdBX(lo!(op0)),
+                0x3A => Self::PitchX(lo!(op0)),
+                0x55 => Self::LdIX(lo!(op0)),
+                0x65 => Self::LdXI(lo!(op0)),
+                0x75 => Self::LdRX(lo!(op0)),
+                0x85 => Self::LdXR(lo!(op0)),
+                _ => return None,
+            },
+            _ => return None,
+        });
+    }
+}
@@ -0,0 +1,42 @@
+// One of the 16 keys of the hex keypad, the discriminant is its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    K0 = 0x0,
+    K1 = 0x1,
+    K2 = 0x2,
+    K3 = 0x3,
+    K4 = 0x4,
+    K5 = 0x5,
+    K6 = 0x6,
+    K7 = 0x7,
+    K8 = 0x8,
+    K9 = 0x9,
+    KA = 0xA,
+    KB = 0xB,
+    KC = 0xC,
+    KD = 0xD,
+    KE = 0xE,
+    KF = 0xF,
+}
+
+impl Key {
+    pub const ALL: [Key; 16] = [
+        Key::K0, Key::K1, Key::K2, Key::K3,
+        Key::K4, Key::K5, Key::K6, Key::K7,
+        Key::K8, Key::K9, Key::KA, Key::KB,
+        Key::KC, Key::KD, Key::KE, Key::KF,
+    ];
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        return Self::ALL.get(index as usize).copied();
+    }
+
+    pub fn index(self) -> u8 {
+        return self as u8;
+    }
+
+    // Bit of the key in Chip8::keypad
+    pub fn mask(self) -> u16 {
+        return 1 << self.index();
+    }
+}
@@ -1,488 +1,2222 @@
-use rand::Rng;
-use rand::rngs::ThreadRng;
-
-macro_rules! nnn {
-    ($op0: expr, $op1: expr) => {
-        ((($op0) & 0x0f) as u16) << 8 | (($op1) as u16)
-    };
-}
-
-macro_rules! lo {
-    ($op0: expr) => (($op0) & 0x0f);
-}
-
-macro_rules! hi {
-    ($op0: expr) => ((($op0) & 0xf0) >> 4);
-}
-
-const FONT: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80  // F
-];
-
-const MEMORY_SIZE: usize = 4096;
-const RESERVED_MEMORY_SIZE: usize = 512;
-const REGISTERS: usize = 16;
-const FRAME_DURATION: isize = 16666;
-
-pub const DISPLAY_WIDTH: usize = 64;
-pub const DISPLAY_HEIGHT: usize = 32;
-pub const DISPLAY_SIZE: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT / 8;
-
-pub struct Chip8 {
-    rng: ThreadRng,
-    pub memory: [u8; MEMORY_SIZE],
-    pub pc: u16,
-    pub i: u16,
-    pub stack: Vec<u16>,
-    pub delay_timer: u8,
-    pub sound_timer: u8,
-    pub registers: [u8; REGISTERS],
-    pub display: [u8; DISPLAY_SIZE],
-    pub keypad: u16,
-}
-
-impl Chip8 {
-    pub fn new() -> Self {
-        let rng = rand::thread_rng();
-        let mut memory = [0; MEMORY_SIZE];
-        memory[0..FONT.len()].copy_from_slice(&FONT);
-        return Self {
-            rng,
-            memory,
-            pc: (RESERVED_MEMORY_SIZE) as u16,
-            i: 0,
-            stack: vec![],
-            delay_timer: 0,
-            sound_timer: 0,
-            registers: [0; REGISTERS],
-            display: [0; DISPLAY_SIZE],
-            keypad: 0,
-        };
-    }
-
-    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), String> {
-        if rom.len() > MEMORY_SIZE - RESERVED_MEMORY_SIZE {
-            return Err("not enough memory to load rom".to_string());
-        }
-        self.memory[RESERVED_MEMORY_SIZE..RESERVED_MEMORY_SIZE + rom.len()].copy_from_slice(rom);
-        return Ok(());
-    }
-
-    pub fn frame(&mut self) -> Result<(), String> {
-        if self.delay_timer != 0 {
-            self.delay_timer -= 1;
-        }
-        if self.sound_timer != 0 {
-            self.sound_timer -= 1;
-        }
-        let mut time: isize = FRAME_DURATION;
-        while time > 0 {
-            if self.pc as usize >= MEMORY_SIZE - 1 {
-                return Err("pc out of memory bounds".to_string());
-            }
-            let op0 = self.memory[self.pc as usize];
-            let op1 = self.memory[(self.pc + 1) as usize];
-            self.pc += 2;
-            let op_time = self.step(op0, op1)?;
-            time -= op_time as isize;
-        }
-        return Ok(());
-    }
-
-    pub fn step(&mut self, op0: u8, op1: u8) -> Result<usize, String> {
-        // println!("0x{:x}{:x}{:x}{:x}", hi!(op0), lo!(op0), hi!(op1), lo!(op1));
-        return Ok(match op0 & 0xf0 {
-            0x00 => match op1 {
-                // 00e0
-                0xe0 => self.op_cls(),
-                0xee => self.op_ret(),
-                _ => {
-                    return Err(format!("Invalid op {:x}{:x}{:x}{:x}", hi!(op0), lo!(op0), hi!(op1), lo!(op1)));
-                }
-            }
-            // 1nnn
-            0x10 => self.op_jp(nnn!(op0, op1)),
-            // 2nnn
-            0x20 => self.op_call(nnn!(op0, op1)),
-            // 3xnn
-            0x30 => self.op_se(lo!(op0), op1),
-            // 4xnn
-            0x40 => self.op_sne(lo!(op0), op1),
-            // 5xy0
-            0x50 => self.op_sexy(lo!(op0), hi!(op1)),
-            // 6xnn
-            0x60 => self.op_ld(lo!(op0), op1),
-            // 7xnn
-            0x70 => self.op_add(lo!(op0), op1),
-            0x80 => match op1 & 0x0f {
-                // 8xy0
-                0x00 => self.op_ldxy(lo!(op0), hi!(op1)),
-                // 8xy1
-                0x01 => self.op_orxy(lo!(op0), hi!(op1)),
-                // 8xy2
-                0x02 => self.op_andxy(lo!(op0), hi!(op1)),
-                // 8xy3
-                0x03 => self.op_xorxy(lo!(op0), hi!(op1)),
-                // 8xy4
-                0x04 => self.op_addxy(lo!(op0), hi!(op1)),
-                // 8xy5
-                0x05 => self.op_subxy(lo!(op0), hi!(op1)),
-                // 8xy6
-                0x06 => self.op_shrxy(lo!(op0)),
-                // 8xy7
-                0x07 => self.op_subnxy(lo!(op0), hi!(op1)),
-                // 8xyE
-                0x0E => self.op_shlxy(lo!(op0)),
-                _ => {
-                    return Err(format!("Invalid op {:x}{:x}{:x}{:x}", hi!(op0), lo!(op0), hi!(op1), lo!(op1)));
-                }
-            }
-            // 9xy0
-            0x90 => self.op_snexy(lo!(op0), hi!(op1)),
-            // Annn
-            0xA0 => self.op_ldi(nnn!(op0, op1)),
-            // Bnnn
-            0xB => self.op_jp0(nnn!(op0, op1)),
-            // Cxkk
-            0xC0 => self.op_rndx(lo!(op0), op1),
-            // Dxyn
-            0xD0 => self.op_drw(lo!(op0), hi!(op1), lo!(op1)),
-            0xE0 => match op1 {
-                //Ex9E
-                0x9E => self.op_skpx(lo!(op0)),
-                //ExA1
-                0xA1 => self.op_sknpx(lo!(op0)),
-                _ => {
-                    return Err(format!("Invalid op {:x}{:x}{:x}{:x}", hi!(op0), lo!(op0), hi!(op1), lo!(op1)));
-                }
-            }
-            0xF0 => match op1 {
-                // 0xFx07
-                0x07 => self.op_ldxdt(lo!(op0)),
-                // 0Fx0A
-                0x0A => self.op_ldxk(lo!(op0)),
-                // 0xFx15
-                0x15 => self.op_lddtx(lo!(op0)),
-                // 0xFx15
-                0x18 => self.op_ldstx(lo!(op0)),
-                // 0xFx1E
-                0x1E => self.op_addix(lo!(op0)),
-                // 0xFx29
-                0x29 => self.op_ldfx(lo!(op0)),
-                // 0xFx33
-                0x33 => self.op_ldbx(lo!(op0)),
-                // 0xFx55
-                0x55 => self.op_ldix(lo!(op0)),
-                // 0xFx65
-                0x65 => self.op_ldxi(lo!(op0)),
-                _ => {
-                    return Err(format!("Invalid op {:x}{:x}{:x}{:x}", hi!(op0), lo!(op0), hi!(op1), lo!(op1)));
-                }
-            },
-            _ => {
-                return Err(format!("Invalid op {:x}{:x}{:x}{:x}", hi!(op0), lo!(op0), hi!(op1), lo!(op1)));
-            }
-        });
-    }
-
-    // 00e0
-    fn op_cls(&mut self) -> usize {
-        self.display.fill(0);
-        return 109;
-    }
-
-    // 00e0
-    fn op_ret(&mut self) -> usize {
-        let addr = self.stack.pop().unwrap();
-        self.pc = addr;
-        return 105;
-    }
-
-    // 1nnn
-    fn op_jp(&mut self, addr: u16) -> usize {
-        self.pc = addr;
-        return 105;
-    }
-
-    // 2nnn
-    fn op_call(&mut self, addr: u16) -> usize {
-        self.stack.push(self.pc);
-        self.pc = addr;
-        return 105;
-    }
-
-    // 3xnn
-    fn op_se(&mut self, vx: u8, byte: u8) -> usize {
-        if self.registers[vx as usize] == byte {
-            self.pc += 2;
-            return 64;
-        }
-        return 46;
-    }
-
-    // 4xnn
-    fn op_sne(&mut self, vx: u8, byte: u8) -> usize {
-        if self.registers[vx as usize] != byte {
-            self.pc += 2;
-            return 64;
-        }
-        return 46;
-    }
-
-    // 5xy0
-    fn op_sexy(&mut self, vx: u8, vy: u8) -> usize {
-        if self.registers[vx as usize] == self.registers[vy as usize] {
-            self.pc += 2;
-            return 82;
-        }
-        return 64;
-    }
-
-    // 6xnn
-    fn op_ld(&mut self, vx: u8, byte: u8) -> usize {
-        self.registers[vx as usize] = byte;
-        return 27;
-    }
-
-    // 7xnn
-    fn op_add(&mut self, vx: u8, byte: u8) -> usize {
-        self.registers[vx as usize] = self.registers[vx as usize].wrapping_add(byte);
-        return 45;
-    }
-
-    // 8xy0
-    fn op_ldxy(&mut self, vx: u8, vy: u8) -> usize {
-        self.registers[vx as usize] = self.registers[vy as usize];
-        return 200;
-    }
-
-    // 8xy1
-    fn op_orxy(&mut self, vx: u8, vy: u8) -> usize {
-        self.registers[vx as usize] |= self.registers[vy as usize];
-        return 200;
-    }
-
-    // 8xy2
-    fn op_andxy(&mut self, vx: u8, vy: u8) -> usize {
-        self.registers[vx as usize] &= self.registers[vy as usize];
-        return 200;
-    }
-
-    // 8xy3
-    fn op_xorxy(&mut self, vx: u8, vy: u8) -> usize {
-        self.registers[vx as usize] ^= self.registers[vy as usize];
-        return 200;
-    }
-
-    // 8xy4
-    fn op_addxy(&mut self, vx: u8, vy: u8) -> usize {
-        let (result, overflows) = self.registers[vx as usize]
-            .overflowing_add(self.registers[vy as usize]);
-        self.registers[vx as usize] = result;
-        self.registers[0xf] = if overflows { 1 } else { 0 };
-        return 200;
-    }
-
-    // 8xy5
-    fn op_subxy(&mut self, vx: u8, vy: u8) -> usize {
-        let (result, overflows) = self.registers[vx as usize]
-            .overflowing_sub(self.registers[vy as usize]);
-        self.registers[vx as usize] = result;
-        self.registers[0xf] = if overflows { 0 } else { 1 };
-        return 200;
-    }
-
-    // 8xy6
-    fn op_shrxy(&mut self, vx: u8) -> usize {
-        let x = self.registers[vx as usize];
-        let (res, _) = x.overflowing_shr(1);
-        self.registers[vx as usize] = res;
-        self.registers[0xf] = x & 0b00000001;
-        return 200;
-    }
-
-    // 8xy7
-    fn op_subnxy(&mut self, vx: u8, vy: u8) -> usize {
-        let (result, overflows) = self.registers[vy as usize]
-            .overflowing_sub(self.registers[vx as usize]);
-        self.registers[vx as usize] = result;
-        self.registers[0xf] = if overflows { 0 } else { 1 };
-        return 200;
-    }
-
-    // 8xyE
-    fn op_shlxy(&mut self, vx: u8) -> usize {
-        let x = self.registers[vx as usize];
-        let (res, _) = x.overflowing_shl(1);
-        self.registers[vx as usize] = res;
-        self.registers[0xf] = (x & 0b10000000) >> 7;
-        return 200;
-    }
-
-    // 9xy0
-    fn op_snexy(&mut self, vx: u8, vy: u8) -> usize {
-        if self.registers[vx as usize] != self.registers[vy as usize] {
-            self.pc += 2;
-            return 82;
-        }
-        return 64;
-    }
-
-    // Annn
-    fn op_ldi(&mut self, addr: u16) -> usize {
-        self.i = addr;
-        return 55;
-    }
-
-    // Bnnn
-    fn op_jp0(&mut self, addr: u16) -> usize {
-        self.pc = addr + self.registers[0x0] as u16;
-        return 105;
-    }
-
-    // Cxkk
-    fn op_rndx(&mut self, vx: u8, byte: u8) -> usize {
-        let r: u8 = self.rng.gen();
-        self.registers[vx as usize] = r & byte;
-        return 164;
-    }
-
-    // Dxyn
-    fn op_drw(&mut self, vx: u8, vy: u8, nibble: u8) -> usize {
-        let x = self.registers[vx as usize];
-        let y = self.registers[vy as usize];
-        let display_x = x as usize % DISPLAY_WIDTH;
-        let shift = x % 8;
-        let display_column_left = display_x / 8;
-        let display_column_right = (display_column_left + 1) % (DISPLAY_WIDTH / 8);
-        let mut prev: u8 = 0;
-
-        for idx in 0..nibble as usize {
-            let display_y = (y as usize + idx) % DISPLAY_HEIGHT;
-            let row = display_y * DISPLAY_WIDTH / 8;
-            let byte = self.memory[self.i as usize + idx];
-
-            let shifted_left = byte >> shift;
-            let prev_left = &mut self.display[row + display_column_left];
-            *prev_left ^= shifted_left;
-            prev |= *prev_left & shifted_left;
-
-            if shift > 0 {
-                let shifted_right = byte << (8 - shift);
-                let prev_right = &mut self.display[row + display_column_right];
-                *prev_right ^= shifted_right;
-                prev |= *prev_right & shifted_right;
-            }
-        }
-        self.registers[0xf] = if prev != 0 { 1 } else { 0 };
-        return 22734;
-    }
-
-    // Ex9E
-    fn op_skpx(&mut self, vx: u8) -> usize {
-        let x = self.registers[vx as usize];
-        if self.keypad & ((1 as u16) << x) != 0 {
-            self.pc += 2;
-            return 64;
-        }
-        return 82;
-    }
-
-    // ExA1
-    fn op_sknpx(&mut self, vx: u8) -> usize {
-        let x = self.registers[vx as usize];
-        if self.keypad & ((1 as u16) << x) == 0 {
-            self.pc += 2;
-            return 64;
-        }
-        return 82;
-    }
-
-    // Fx07
-    fn op_ldxdt(&mut self, vx: u8) -> usize {
-        self.registers[vx as usize] = self.delay_timer;
-        return 45;
-    }
-
-    // Fx0A
-    fn op_ldxk(&mut self, vx: u8) -> usize {
-        for i in 0..16 {
-            if 1 << i & self.keypad != 0 {
-                self.registers[vx as usize] = i as u8;
-                return 200;
-            }
-        }
-        self.pc -= 2;
-        return FRAME_DURATION as usize;
-    }
-
-    // Fx15
-    fn op_lddtx(&mut self, vx: u8) -> usize {
-        self.delay_timer = self.registers[vx as usize];
-        return 45;
-    }
-
-    // Fx18
-    fn op_ldstx(&mut self, vx: u8) -> usize {
-        self.sound_timer = self.registers[vx as usize];
-        return 45;
-    }
-
-    // Fx1e
-    fn op_addix(&mut self, vx: u8) -> usize {
-        let x = self.registers[vx as usize];
-        self.i = self.i.wrapping_add(x as u16);
-        return 86;
-    }
-
-    // Fx29
-    fn op_ldfx(&mut self, vx: u8) -> usize {
-        let x = self.registers[vx as usize];
-        self.i = (x as u16) * 5;
-        return 91;
-    }
-
-    // Fx33
-    fn op_ldbx(&mut self, vx: u8) -> usize {
-        let x = self.registers[vx as usize];
-        let first = x / 100;
-        let second = x / 10 % 10;
-        let third = x % 10;
-        self.memory[self.i as usize] = first;
-        self.memory[self.i as usize + 1] = second;
-        self.memory[self.i as usize + 2] = third;
-        return 364 + (first as usize + second as usize + third as usize) * 73;
-    }
-
-    // Fx55
-    fn op_ldix(&mut self, vx: u8) -> usize {
-        for i in 0..(vx + 1) {
-            let v = self.registers[i as usize];
-            self.memory[i as usize + self.i as usize] = v;
-        }
-        return 64 * (vx as usize + 2);
-    }
-
-    // Fx65
-    fn op_ldxi(&mut self, vx: u8) -> usize {
-        for i in 0..(vx + 1) {
-            self.registers[i as usize] = self.memory[i as usize + self.i as usize];
-        }
-        return 64 * (vx as usize + 2);
-    }
+#![allow(clippy::needless_return)]
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{ErrorKind, Read};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+macro_rules! nnn {
+    ($op0: expr, $op1: expr) => {
+        ((($op0) & 0x0f) as u16) << 8 | (($op1) as u16)
+    };
+}
+
+macro_rules! opcode {
+    ($op0: expr, $op1: expr) => {
+        (($op0) as u16) << 8 | (($op1) as u16)
+    };
+}
+
+macro_rules! lo {
+    ($op0: expr) => (($op0) & 0x0f);
+}
+
+macro_rules! hi {
+    ($op0: expr) => ((($op0) & 0xf0) >> 4);
+}
+
+mod config;
+mod disasm;
+mod instruction;
+mod key;
+mod opcodes;
+mod quirks;
+mod state;
+mod threaded;
+
+pub use config::Chip8Config;
+pub use disasm::disassemble;
+pub use instruction::Instruction;
+pub use key::Key;
+pub use opcodes::{opcode_info, supported_opcodes, OpcodeInfo};
+pub use quirks::Quirks;
+pub use threaded::{Chip8Thread, Command, Output};
+
+const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80  // F
+];
+
+// SUPER-CHIP 8x10 digits for Fx30, with Octo's A-F after 0-9
+const BIG_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0  // F
+];
+
+// Where the fonts live in the reserved area, Fx29 points i into the small
+// 4x5 one and Fx30 into the big 8x10 one right after it
+pub const FONT_ADDRESS: usize = 0x000;
+pub const BIG_FONT_ADDRESS: usize = FONT_ADDRESS + FONT.len();
+
+const _: () = assert!(BIG_FONT_ADDRESS + BIG_FONT.len() <= RESERVED_MEMORY_SIZE);
+
+const RESERVED_MEMORY_SIZE: usize = 512;
+const PBM_LINE_WIDTH: usize = 70;
+const REGISTERS: usize = 16;
+const DEFAULT_MAX_STACK_DEPTH: usize = 16;
+const DEFAULT_TONE_HZ: u16 = 440;
+const FRAME_DURATION: isize = 16666;
+const INVALID_OP_DURATION: usize = 40;
+// Most an instruction may cost under set_check_cycle_costs, Dxyn waits for
+// the display and takes a little more than a frame
+const MAX_OP_CYCLES: usize = 2 * FRAME_DURATION as usize;
+const DEFAULT_IDLE_FRAMES: u64 = 120;
+// Highest timer phase, short of the decrement so a fractional timer never
+// reaches the next whole value before the timer actually gets there
+const MAX_TIMER_PHASE: f32 = 0.999;
+
+// The CHIP-8 address space, and the 64K XO-CHIP has. The memory array is
+// always the larger size, only memory_size of it is addressable
+pub const MEMORY_SIZE: usize = 4096;
+pub const XO_MEMORY_SIZE: usize = 65536;
+// The CHIP-8 display, and the SUPER-CHIP hires one 00FF switches to
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+// Bytes in the display buffer, enough for hires. Rows are packed at the
+// current width, lores only uses the first DISPLAY_WIDTH * DISPLAY_HEIGHT / 8
+pub const DISPLAY_SIZE: usize = HIRES_WIDTH * HIRES_HEIGHT / 8;
+// Bytes F002 loads, 128 one-bit samples
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+// SUPER-CHIP RPL user flags for Fx75/Fx85. SUPER-CHIP has 8 of them,
+// XO-CHIP all 16
+pub const RPL_FLAGS: usize = 16;
+
+// The COSMAC VIP hex keypad, row by row
+pub const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+// Fx33 takes longer the larger the sum of the decimal digits is
+const fn bcd_cycles(value: u8) -> usize {
+    let digits = value / 100 + value / 10 % 10 + value % 10;
+    return 364 + digits as usize * 73;
+}
+
+// Fx55/Fx65 take longer the more registers are copied
+const fn bulk_transfer_cycles(vx: u8) -> usize {
+    return 64 * (vx as usize + 2);
+}
+
+const _: () = assert!(bcd_cycles(0) == 364);
+const _: () = assert!(bcd_cycles(255) == 364 + 12 * 73);
+const _: () = assert!(bulk_transfer_cycles(0x0) == 128);
+const _: () = assert!(bulk_transfer_cycles(0xF) == 1088);
+
+// XO-CHIP playback rate for an Fx3A pitch byte, 64 being 4000Hz
+pub fn pitch_frequency(pitch: u8) -> f32 {
+    return 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+}
+
+// Content hash of a rom with the same stable FNV-1a as display_hash, e.g. to
+// recognize a game whatever its file is called
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    return fnv1a(rom);
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
+
+fn load_fonts(memory: &mut [u8]) {
+    memory[FONT_ADDRESS..FONT_ADDRESS + FONT.len()].copy_from_slice(&FONT);
+    memory[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+}
+
+fn check_rom_size(size: usize, capacity: usize) -> Result<(), String> {
+    if size > capacity {
+        return Err(format!("rom is {} bytes but only {} bytes are available", size, capacity));
+    }
+    return Ok(());
+}
+
+// Static check of a rom before loading it into a machine with capacity
+// bytes of room, rom_capacity or a configured machine's capacity. Fails if
+// it cannot be loaded, otherwise lists the addresses of words that do not
+// decode, which are usually sprite data rather than code
+pub fn verify_rom(rom: &[u8], capacity: usize) -> Result<Vec<u16>, String> {
+    if rom.is_empty() {
+        return Err("empty rom".to_string());
+    }
+    check_rom_size(rom.len(), capacity)?;
+    return Ok(rom
+        .chunks_exact(2)
+        .enumerate()
+        .filter(|(_, op)| Instruction::decode(op[0], op[1]).is_none())
+        .map(|(idx, _)| (RESERVED_MEMORY_SIZE + idx * 2) as u16)
+        .collect());
+}
+
+// Display pixel under window coordinates (x, y), for a display drawn at
+// (origin_x, origin_y) scale window pixels wide, size being the display's
+pub fn window_to_pixel(
+    x: i32,
+    y: i32,
+    origin_x: i32,
+    origin_y: i32,
+    scale: u32,
+    size: (usize, usize),
+) -> Option<(usize, usize)> {
+    if x < origin_x || y < origin_y || scale == 0 {
+        return None;
+    }
+    let pixel_x = ((x - origin_x) as u32 / scale) as usize;
+    let pixel_y = ((y - origin_y) as u32 / scale) as usize;
+    if pixel_x >= size.0 || pixel_y >= size.1 {
+        return None;
+    }
+    return Some((pixel_x, pixel_y));
+}
+
+// Index in the display buffer of the byte holding pixel (x, y) on a display
+// width pixels wide
+pub fn pixel_byte_index(x: usize, y: usize, width: usize) -> usize {
+    return y * width / 8 + x / 8;
+}
+
+// (frame, keypad) pairs, an entry is recorded each time the keypad changes
+pub type InputLog = Vec<(u64, u16)>;
+
+// Receives (pc, op0, op1) for every instruction run by tick
+pub type TraceHook = Box<dyn FnMut(u16, u8, u8)>;
+
+// Receives all the RPL flags each time Fx75 saves some
+pub type FlagsHook = Box<dyn FnMut(&[u8; RPL_FLAGS])>;
+
+// Gets a shot at opcodes step does not know, Some(cycles) when it handled
+// the opcode and None to fall through to the invalid op policy
+pub type CustomOpcodeHandler = Box<dyn FnMut(&mut Chip8Context<'_>, u8, u8) -> Option<usize>>;
+
+// Machine state handed to a custom opcode handler
+pub struct Chip8Context<'a> {
+    pub memory: &'a mut [u8; XO_MEMORY_SIZE],
+    pub pc: &'a mut u16,
+    pub i: &'a mut u16,
+    pub stack: &'a mut Vec<u16>,
+    pub delay_timer: &'a mut u8,
+    pub sound_timer: &'a mut u8,
+    pub registers: &'a mut [u8; REGISTERS],
+    pub display: &'a mut [u8; DISPLAY_SIZE],
+    pub keypad: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidOpPolicy {
+    #[default]
+    Error,
+    // pc has already moved past the opcode, so it is simply ignored. Other
+    // errors inside a frame are skipped the same way, every skipped opcode
+    // is recorded in drain_diagnostics
+    SkipAsNop,
+    Halt,
+}
+
+// What happens when a rom writes to the interpreter area below 0x200
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReservedWritePolicy {
+    #[default]
+    Allow,
+    Error,
+    Ignore,
+}
+
+// How a frame ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOutcome {
+    Completed,
+    // Blocked on Fx0A, nothing changes until a key goes down so the caller
+    // can sleep until input instead of running more frames
+    WaitingForKey,
+    // Stuck in a jump to itself, or nothing visible happened for a while.
+    // Only reported once enabled with set_detect_halt_loop
+    Idle,
+    Halted,
+}
+
+// A Dxyn execution, x and y being the register values rather than indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawEvent {
+    pub pc: u16,
+    pub x: u8,
+    pub y: u8,
+    pub height: u8,
+    pub collided: bool,
+}
+
+// Snapshot of how the machine is set up and where it is, for status lines
+// and diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineInfo {
+    pub width: usize,
+    pub height: usize,
+    pub quirks: Quirks,
+    pub cycles_per_frame: usize,
+    pub instructions_per_frame: Option<usize>,
+    pub halted: bool,
+    pub waiting_for_key: bool,
+    pub instruction_count: u64,
+    pub frame_count: u64,
+    pub stack_depth: usize,
+}
+
+// State before a tick, memory is only kept as the bytes the tick overwrote
+struct Snapshot {
+    pc: u16,
+    i: u16,
+    stack: Vec<u16>,
+    delay_timer: u8,
+    sound_timer: u8,
+    registers: [u8; REGISTERS],
+    display: [u8; DISPLAY_SIZE],
+    plane2: [u8; DISPLAY_SIZE],
+    planes: u8,
+    hires: bool,
+    halted: bool,
+    waiting_for_key: bool,
+    waiting_for_display: bool,
+    pitch: Option<u8>,
+    audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    rpl_flags: [u8; RPL_FLAGS],
+    memory_writes: Vec<(usize, u8)>,
+}
+
+pub struct Chip8 {
+    rng: StdRng,
+    load_default_font: bool,
+    rng_constant: Option<u8>,
+    last_random: Option<u8>,
+    cycles_per_frame: usize,
+    rom_size: usize,
+    instructions_per_frame: Option<usize>,
+    wide_sprites: bool,
+    strict_opcodes: bool,
+    frame_count: u64,
+    instruction_count: u64,
+    collision_count: u64,
+    input_log: Option<InputLog>,
+    invalid_op_policy: InvalidOpPolicy,
+    halted: bool,
+    waiting_for_key: bool,
+    detect_halt_loop: bool,
+    check_cycle_costs: bool,
+    freeze_timers: bool,
+    // Frames elapsed since the timers were last decremented, advanced by
+    // the frontend
+    timer_phase: f32,
+    drew_this_frame: bool,
+    waiting_for_display: bool,
+    idle_frame_threshold: u64,
+    self_jump: bool,
+    unchanged_frames: u64,
+    last_display_hash: u64,
+    halt_hook: Option<Box<dyn FnMut()>>,
+    trace_hook: Option<TraceHook>,
+    rpl_flags: [u8; RPL_FLAGS],
+    flags_hook: Option<FlagsHook>,
+    pokes: Vec<(u16, u8)>,
+    breakpoints: HashSet<u16>,
+    custom_opcode_handler: Option<CustomOpcodeHandler>,
+    max_stack_depth: Option<usize>,
+    tone_hz: Option<u16>,
+    pitch: Option<u8>,
+    audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    profiling: bool,
+    opcode_histogram: HashMap<u16, u64>,
+    tapped_keys: u16,
+    queued_keys: VecDeque<Key>,
+    // Keys queue_key_press held down in the last frame
+    queued_last_frame: u16,
+    reserved_write_policy: ReservedWritePolicy,
+    draw_events: Option<Vec<DrawEvent>>,
+    collision_pixels: Option<Vec<(usize, usize)>>,
+    rewind_capacity: usize,
+    history: VecDeque<Snapshot>,
+    pending_writes: Vec<(usize, u8)>,
+    diagnostics: Vec<String>,
+    hires: bool,
+    xochip: bool,
+    // Bit 0 for the display, bit 1 for plane2, what Dxyn and 00E0 work on
+    planes: u8,
+    // The second XO-CHIP display plane, laid out like display
+    plane2: [u8; DISPLAY_SIZE],
+    pub memory: [u8; XO_MEMORY_SIZE],
+    pub pc: u16,
+    pub i: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub registers: [u8; REGISTERS],
+    pub display: [u8; DISPLAY_SIZE],
+    pub keypad: u16,
+    pub quirks: Quirks,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The framebuffer as rows of '#' for lit pixels and ' ' for unlit ones
+impl fmt::Display for Chip8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.display_buffer().chunks(self.display_width() / 8) {
+            for byte in row {
+                for bit in (0..8).rev() {
+                    let pixel = if byte >> bit & 1 != 0 { '#' } else { ' ' };
+                    write!(f, "{}", pixel)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        return Ok(());
+    }
+}
+
+impl Chip8 {
+    pub fn new() -> Self {
+        return Self::new_with_config(Chip8Config::default());
+    }
+
+    pub fn new_with_config(config: Chip8Config) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut memory = [0; XO_MEMORY_SIZE];
+        if config.load_default_font {
+            load_fonts(&mut memory);
+        }
+        return Self {
+            rng,
+            load_default_font: config.load_default_font,
+            rng_constant: None,
+            last_random: None,
+            cycles_per_frame: config.cycles_per_frame,
+            rom_size: 0,
+            instructions_per_frame: config.instructions_per_frame,
+            wide_sprites: config.wide_sprites,
+            strict_opcodes: config.strict_opcodes,
+            frame_count: 0,
+            instruction_count: 0,
+            collision_count: 0,
+            input_log: None,
+            invalid_op_policy: config.invalid_op_policy,
+            halted: false,
+            waiting_for_key: false,
+            detect_halt_loop: false,
+            check_cycle_costs: false,
+            freeze_timers: false,
+            timer_phase: 0.0,
+            drew_this_frame: false,
+            waiting_for_display: false,
+            idle_frame_threshold: DEFAULT_IDLE_FRAMES,
+            self_jump: false,
+            unchanged_frames: 0,
+            last_display_hash: 0,
+            halt_hook: None,
+            trace_hook: None,
+            rpl_flags: [0; RPL_FLAGS],
+            flags_hook: None,
+            pokes: vec![],
+            breakpoints: HashSet::new(),
+            custom_opcode_handler: None,
+            max_stack_depth: config.max_stack_depth,
+            tone_hz: config.tone_hz,
+            pitch: None,
+            audio_pattern: None,
+            profiling: false,
+            opcode_histogram: HashMap::new(),
+            tapped_keys: 0,
+            queued_keys: VecDeque::new(),
+            queued_last_frame: 0,
+            reserved_write_policy: config.reserved_write_policy,
+            draw_events: None,
+            collision_pixels: None,
+            rewind_capacity: 0,
+            history: VecDeque::new(),
+            pending_writes: vec![],
+            diagnostics: vec![],
+            memory,
+            pc: (RESERVED_MEMORY_SIZE) as u16,
+            i: 0,
+            stack: vec![],
+            delay_timer: 0,
+            sound_timer: 0,
+            registers: [0; REGISTERS],
+            display: [0; DISPLAY_SIZE],
+            hires: false,
+            xochip: config.xochip,
+            planes: 1,
+            plane2: [0; DISPLAY_SIZE],
+            keypad: 0,
+            quirks: config.quirks,
+        };
+    }
+
+    pub fn with_quirks(quirks: Quirks) -> Self {
+        return Self::new_with_config(Chip8Config { quirks, ..Chip8Config::default() });
+    }
+
+    // Puts the machine back in its power-on state, configuration, rng and
+    // RPL flags are kept and the rom has to be loaded again
+    pub fn reset(&mut self) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("reset");
+        self.memory = [0; XO_MEMORY_SIZE];
+        if self.load_default_font {
+            load_fonts(&mut self.memory);
+        }
+        self.pc = RESERVED_MEMORY_SIZE as u16;
+        self.i = 0;
+        self.stack.clear();
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.timer_phase = 0.0;
+        self.registers = [0; REGISTERS];
+        self.display = [0; DISPLAY_SIZE];
+        self.hires = false;
+        self.planes = 1;
+        self.plane2 = [0; DISPLAY_SIZE];
+        self.keypad = 0;
+        self.tapped_keys = 0;
+        self.queued_keys.clear();
+        self.queued_last_frame = 0;
+        self.halted = false;
+        self.waiting_for_key = false;
+        self.drew_this_frame = false;
+        self.waiting_for_display = false;
+        self.self_jump = false;
+        self.unchanged_frames = 0;
+        self.pitch = None;
+        self.audio_pattern = None;
+        self.frame_count = 0;
+        self.instruction_count = 0;
+        self.collision_count = 0;
+        self.rom_size = 0;
+        self.history.clear();
+        self.diagnostics.clear();
+        if let Some(log) = &mut self.input_log {
+            log.clear();
+        }
+    }
+
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // Makes Cxkk load value & kk instead of a random byte, for runs that
+    // must not depend on the rand crate
+    pub fn set_rng_constant(&mut self, value: Option<u8>) {
+        self.rng_constant = value;
+    }
+
+    // The byte the last Cxkk drew before masking it with kk, to check the
+    // rng itself rather than what a rom did with it
+    pub fn last_random(&self) -> Option<u8> {
+        return self.last_random;
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles: usize) {
+        self.cycles_per_frame = cycles;
+    }
+
+    pub fn set_instructions_per_frame(&mut self, count: Option<usize>) {
+        self.instructions_per_frame = count;
+    }
+
+    pub fn cycles_per_frame(&self) -> usize {
+        return self.cycles_per_frame;
+    }
+
+    pub fn instructions_per_frame(&self) -> Option<usize> {
+        return self.instructions_per_frame;
+    }
+
+    pub fn set_wide_sprites(&mut self, enabled: bool) {
+        self.wide_sprites = enabled;
+    }
+
+    pub fn set_strict_opcodes(&mut self, enabled: bool) {
+        self.strict_opcodes = enabled;
+    }
+
+    pub fn set_invalid_op_policy(&mut self, policy: InvalidOpPolicy) {
+        self.invalid_op_policy = policy;
+    }
+
+    // What SkipAsNop skipped since the last call, oldest first
+    pub fn drain_diagnostics(&mut self) -> Vec<String> {
+        return std::mem::take(&mut self.diagnostics);
+    }
+
+    pub fn set_reserved_write_policy(&mut self, policy: ReservedWritePolicy) {
+        self.reserved_write_policy = policy;
+    }
+
+    pub fn register(&self, idx: usize) -> Option<u8> {
+        return self.registers.get(idx).copied();
+    }
+
+    // Returns false and leaves the registers untouched if idx is not V0..VF
+    pub fn set_register(&mut self, idx: usize, value: u8) -> bool {
+        return match self.registers.get_mut(idx) {
+            Some(register) => {
+                *register = value;
+                true
+            }
+            None => false,
+        };
+    }
+
+    pub fn pc(&self) -> u16 {
+        return self.pc;
+    }
+
+    pub fn i(&self) -> u16 {
+        return self.i;
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        return self.delay_timer;
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        return self.sound_timer;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        return self.halted;
+    }
+
+    // Called once each time the machine halts, through 00FD or the invalid
+    // op policy
+    pub fn set_halt_hook(&mut self, hook: Box<dyn FnMut()>) {
+        self.halt_hook = Some(hook);
+    }
+
+    pub fn set_custom_opcode_handler(&mut self, handler: CustomOpcodeHandler) {
+        self.custom_opcode_handler = Some(handler);
+    }
+
+    // Called before each instruction tick executes
+    pub fn set_trace_hook(&mut self, hook: TraceHook) {
+        self.trace_hook = Some(hook);
+    }
+
+    // Games keep save data in the RPL flags, frontends persist them from
+    // here and hand them back with set_rpl_flags before the rom runs
+    pub fn set_flags_hook(&mut self, hook: FlagsHook) {
+        self.flags_hook = Some(hook);
+    }
+
+    pub fn rpl_flags(&self) -> &[u8; RPL_FLAGS] {
+        return &self.rpl_flags;
+    }
+
+    // Shorter slices, e.g. 8 flags saved by a SUPER-CHIP emulator, leave the
+    // rest zeroed and extra bytes are ignored
+    pub fn set_rpl_flags(&mut self, flags: &[u8]) {
+        let len = flags.len().min(RPL_FLAGS);
+        self.rpl_flags = [0; RPL_FLAGS];
+        self.rpl_flags[..len].copy_from_slice(&flags[..len]);
+    }
+
+    pub fn stack_snapshot(&self) -> &[u16] {
+        return &self.stack;
+    }
+
+    pub fn call_depth(&self) -> usize {
+        return self.stack.len();
+    }
+
+    // Readable dump of the cpu state for logs and external tools, memory and
+    // display are left out
+    pub fn state_json(&self) -> String {
+        let registers: Vec<String> = self.registers
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+        let stack: Vec<String> = self.stack
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect();
+        return format!(
+            "{{\"pc\":{},\"i\":{},\"delay_timer\":{},\"sound_timer\":{},\"registers\":[{}],\"stack\":[{}],\"keypad\":{}}}",
+            self.pc,
+            self.i,
+            self.delay_timer,
+            self.sound_timer,
+            registers.join(","),
+            stack.join(","),
+            self.keypad,
+        );
+    }
+
+    // One line view for trace logs, e.g. "PC=0200 I=0000 V=00 00 .. 00"
+    pub fn registers_hex(&self) -> String {
+        let registers: Vec<String> = self.registers
+            .iter()
+            .map(|value| format!("{:02X}", value))
+            .collect();
+        return format!(
+            "PC={:04X} I={:04X} DT={:02X} ST={:02X} V={}",
+            self.pc,
+            self.i,
+            self.delay_timer,
+            self.sound_timer,
+            registers.join(" "),
+        );
+    }
+
+    // Keeps the state before each of the last capacity ticks so step_back can
+    // undo them, 0 disables it
+    pub fn enable_rewind(&mut self, capacity: usize) {
+        self.rewind_capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+    }
+
+    // Undoes the most recent tick
+    pub fn step_back(&mut self) -> Result<(), String> {
+        let snapshot = match self.history.pop_back() {
+            Some(snapshot) => snapshot,
+            None => return Err("no history to step back to".to_string()),
+        };
+        for &(addr, value) in snapshot.memory_writes.iter().rev() {
+            self.memory[addr] = value;
+        }
+        self.pc = snapshot.pc;
+        self.i = snapshot.i;
+        self.stack = snapshot.stack;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.registers = snapshot.registers;
+        self.display = snapshot.display;
+        self.plane2 = snapshot.plane2;
+        self.planes = snapshot.planes;
+        self.hires = snapshot.hires;
+        self.halted = snapshot.halted;
+        self.waiting_for_key = snapshot.waiting_for_key;
+        self.waiting_for_display = snapshot.waiting_for_display;
+        self.pitch = snapshot.pitch;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.rpl_flags = snapshot.rpl_flags;
+        return Ok(());
+    }
+
+    // None lets the stack grow without bound
+    pub fn set_max_stack_depth(&mut self, depth: Option<usize>) {
+        self.max_stack_depth = depth;
+    }
+
+    // (index, new value) of every display byte that differs from previous
+    pub fn display_delta(&self, previous: &[u8; DISPLAY_SIZE]) -> Vec<(usize, u8)> {
+        return self.display
+            .iter()
+            .zip(previous.iter())
+            .enumerate()
+            .filter(|(_, (current, previous))| current != previous)
+            .map(|(idx, (current, _))| (idx, *current))
+            .collect();
+    }
+
+    pub fn is_beeping(&self) -> bool {
+        return self.sound_timer > 0;
+    }
+
+    // Pitch of the beep, 440Hz unless overridden
+    pub fn tone_hz(&self) -> u16 {
+        return self.tone_hz.unwrap_or(DEFAULT_TONE_HZ);
+    }
+
+    pub fn set_tone_hz(&mut self, tone_hz: Option<u16>) {
+        self.tone_hz = tone_hz;
+    }
+
+    // Frequency to play the beep at, the Fx3A pitch once a rom has set one
+    // and tone_hz before that
+    pub fn beep_frequency(&self) -> f32 {
+        return match self.pitch {
+            Some(pitch) => pitch_frequency(pitch),
+            None => self.tone_hz() as f32,
+        };
+    }
+
+    // The F002 pattern, None until a rom loads one. Frontends play its bits
+    // high bit first, one per sample at pattern_rate, looping while the
+    // sound timer runs
+    pub fn audio_pattern(&self) -> Option<&[u8; AUDIO_PATTERN_SIZE]> {
+        return self.audio_pattern.as_ref();
+    }
+
+    // Samples per second to play audio_pattern at, the Fx3A pitch or 4000Hz
+    pub fn pattern_rate(&self) -> f32 {
+        return pitch_frequency(self.pitch.unwrap_or(64));
+    }
+
+    // Counts executed opcodes while enabled, see opcode_counts
+    pub fn enable_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+    }
+
+    pub fn opcode_counts(&self) -> &HashMap<u16, u64> {
+        return &self.opcode_histogram;
+    }
+
+    // Records every Dxyn while enabled, see take_draw_events
+    pub fn enable_draw_log(&mut self, enabled: bool) {
+        self.draw_events = if enabled { Some(vec![]) } else { None };
+    }
+
+    pub fn take_draw_events(&mut self) -> Vec<DrawEvent> {
+        return self.draw_events.as_mut().map(std::mem::take).unwrap_or_default();
+    }
+
+    // Records the (x, y) of every pixel a Dxyn turns off while enabled, see
+    // take_collision_pixels
+    pub fn enable_collision_log(&mut self, enabled: bool) {
+        self.collision_pixels = if enabled { Some(vec![]) } else { None };
+    }
+
+    pub fn take_collision_pixels(&mut self) -> Vec<(usize, usize)> {
+        return self.collision_pixels.as_mut().map(std::mem::take).unwrap_or_default();
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        return self.frame_count;
+    }
+
+    pub fn instruction_count(&self) -> u64 {
+        return self.instruction_count;
+    }
+
+    pub fn info(&self) -> MachineInfo {
+        return MachineInfo {
+            width: self.display_width(),
+            height: self.display_height(),
+            quirks: self.quirks,
+            cycles_per_frame: self.cycles_per_frame,
+            instructions_per_frame: self.instructions_per_frame,
+            halted: self.halted,
+            waiting_for_key: self.waiting_for_key,
+            instruction_count: self.instruction_count,
+            frame_count: self.frame_count,
+            stack_depth: self.stack.len(),
+        };
+    }
+
+    // Number of Dxyn draws that set vf
+    pub fn collision_count(&self) -> u64 {
+        return self.collision_count;
+    }
+
+    pub fn reset_collision_count(&mut self) {
+        self.collision_count = 0;
+    }
+
+    pub fn start_recording(&mut self) {
+        self.input_log = Some(vec![]);
+    }
+
+    pub fn input_log(&self) -> Option<&InputLog> {
+        return self.input_log.as_ref();
+    }
+
+    pub fn take_input_log(&mut self) -> Option<InputLog> {
+        return self.input_log.take();
+    }
+
+    // Runs the given number of frames, feeding the keypad from a recorded
+    // log. Starting from the same state and rng seed reproduces the recording
+    pub fn apply_input_log(&mut self, log: &[(u64, u16)], frames: u64) -> Result<(), String> {
+        let mut entries = log.iter().peekable();
+        for _ in 0..frames {
+            while let Some(&&(frame, keypad)) = entries.peek() {
+                if frame > self.frame_count {
+                    break;
+                }
+                self.keypad = keypad;
+                entries.next();
+            }
+            self.frame()?;
+        }
+        return Ok(());
+    }
+
+    // Room for a rom, everything from 0x200 to the end of the CHIP-8 memory
+    pub fn rom_capacity() -> usize {
+        return MEMORY_SIZE - RESERVED_MEMORY_SIZE;
+    }
+
+    // Same as rom_capacity, up to the end of XO-CHIP memory when enabled
+    pub fn capacity(&self) -> usize {
+        return self.memory_size() - RESERVED_MEMORY_SIZE;
+    }
+
+    // What is left after the last rom loaded
+    pub fn free_memory(&self) -> usize {
+        return self.capacity() - self.rom_size;
+    }
+
+    // Addressable bytes, 64K with XO-CHIP extensions and 4K otherwise
+    pub fn memory_size(&self) -> usize {
+        return if self.xochip { XO_MEMORY_SIZE } else { MEMORY_SIZE };
+    }
+
+    // 64K memory, F000 nnnn, Fn01 plane selection and skips that step over
+    // all 4 bytes of F000 nnnn. Without them those opcodes are invalid
+    pub fn set_xochip(&mut self, enabled: bool) {
+        self.xochip = enabled;
+    }
+
+    pub fn is_xochip(&self) -> bool {
+        return self.xochip;
+    }
+
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), String> {
+        check_rom_size(rom.len(), self.capacity())?;
+        self.rom_size = rom.len();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(size = rom.len(), hash = %format_args!("{:016x}", rom_hash(rom)), "rom loaded");
+        self.memory[RESERVED_MEMORY_SIZE..RESERVED_MEMORY_SIZE + rom.len()].copy_from_slice(rom);
+        return Ok(());
+    }
+
+    // Replaces the whole memory, font and reserved area included, e.g. with a
+    // dump taken from another emulator, and resumes at pc
+    pub fn load_memory_image(&mut self, image: &[u8; MEMORY_SIZE], pc: u16) -> Result<(), String> {
+        if pc as usize >= MEMORY_SIZE - 1 {
+            return Err(format!("pc 0x{:03x} out of memory bounds", pc));
+        }
+        self.memory[..MEMORY_SIZE].copy_from_slice(image);
+        self.memory[MEMORY_SIZE..].fill(0);
+        self.pc = pc;
+        return Ok(());
+    }
+
+    // Streams a rom straight into memory, returning its size
+    pub fn load_rom_from<R: Read>(&mut self, reader: &mut R) -> Result<usize, String> {
+        let mut loaded = 0;
+        let capacity = self.capacity();
+        while loaded < capacity {
+            let start = RESERVED_MEMORY_SIZE + loaded;
+            let end = self.memory_size();
+            match reader.read(&mut self.memory[start..end]) {
+                Ok(0) => {
+                    self.rom_size = loaded;
+                    return Ok(loaded);
+                }
+                Ok(n) => loaded += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+        let mut extra = [0u8; 1];
+        loop {
+            match reader.read(&mut extra) {
+                Ok(0) => {
+                    self.rom_size = loaded;
+                    return Ok(loaded);
+                }
+                // The full size is unknown without reading the rest
+                Ok(_) => return Err(format!("rom is over the {} bytes available", capacity)),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+    }
+
+    // Bit n set means key n is down. This is the supported way to feed input,
+    // the keypad field may change representation
+    pub fn set_keypad(&mut self, state: u16) {
+        self.keypad = state;
+    }
+
+    pub fn keypad(&self) -> u16 {
+        return self.keypad;
+    }
+
+    pub fn press_key(&mut self, key: Key) {
+        self.keypad |= key.mask();
+    }
+
+    pub fn release_key(&mut self, key: Key) {
+        self.keypad &= !key.mask();
+    }
+
+    // Keys currently held down, in key order. Taps only show up during the
+    // frame they are held for
+    pub fn pressed_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        return Key::ALL.into_iter().filter(|key| self.keypad & key.mask() != 0);
+    }
+
+    // Holds the key down for the next frame only, it is released when that
+    // frame returns. Fx0A completes on press, so a tap satisfies a pending
+    // Fx0A during the tapped frame.
+    pub fn tap_key(&mut self, key: Key) {
+        self.tapped_keys |= key.mask();
+    }
+
+    // A press that is not lost however quickly the key came back up. Queued
+    // presses go down one a frame, in order, so Fx0A sees each of them, and
+    // a key pressed again is let up for a frame first so it reads as a new
+    // press
+    pub fn queue_key_press(&mut self, key: Key) {
+        self.queued_keys.push_back(key);
+    }
+
+    // Holds down the next queued press, unless it is the key that was down
+    // last frame
+    fn take_queued_keys(&mut self) -> u16 {
+        let keys = match self.queued_keys.front() {
+            Some(key) if self.queued_last_frame & key.mask() == 0 => key.mask(),
+            _ => 0,
+        };
+        if keys != 0 {
+            self.queued_keys.pop_front();
+        }
+        self.queued_last_frame = keys;
+        return keys;
+    }
+
+    pub fn frame(&mut self) -> Result<FrameOutcome, String> {
+        return self.frame_with_timers(true);
+    }
+
+    // Runs a frame worth of cycles without ticking the timers, extra frames
+    // run in between real ones keep the timers at 60Hz
+    pub fn fast_frame(&mut self) -> Result<FrameOutcome, String> {
+        return self.frame_with_timers(false);
+    }
+
+    // Counts both timers down once, the way every frame does. A debugger
+    // stepping with tick can call it whenever it wants time to pass
+    pub fn decrement_timers(&mut self) {
+        self.timer_phase = 0.0;
+        if self.delay_timer != 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer != 0 {
+            self.sound_timer -= 1;
+        }
+    }
+
+    // Lets a frontend rendering faster than 60Hz tell how far it is into the
+    // current frame, frames being the wall time since the last frame in
+    // 1/60s. Stops just short of a whole frame, only a decrement moves on
+    pub fn advance_timer_phase(&mut self, frames: f32) {
+        if self.freeze_timers {
+            return;
+        }
+        self.timer_phase = (self.timer_phase + frames.max(0.0)).min(MAX_TIMER_PHASE);
+    }
+
+    // The delay timer interpolated toward its next value by the timer phase,
+    // always above the value it goes down to next. 0 stays 0
+    pub fn delay_timer_fractional(&self) -> f32 {
+        return self.fractional_timer(self.delay_timer);
+    }
+
+    // Same as delay_timer_fractional for the sound timer, e.g. to fade a beep out
+    pub fn sound_timer_fractional(&self) -> f32 {
+        return self.fractional_timer(self.sound_timer);
+    }
+
+    fn fractional_timer(&self, timer: u8) -> f32 {
+        if timer == 0 {
+            return 0.0;
+        }
+        return timer as f32 - self.timer_phase;
+    }
+
+    fn frame_with_timers(&mut self, timers: bool) -> Result<FrameOutcome, String> {
+        let held = self.keypad;
+        self.keypad |= std::mem::take(&mut self.tapped_keys) | self.take_queued_keys();
+        let result = self.run_frame(timers);
+        self.keypad = held;
+        return result;
+    }
+
+    // Writes value to addr at the start of every frame, e.g. to freeze a
+    // game's lives counter
+    pub fn add_poke(&mut self, addr: u16, value: u8) {
+        self.pokes.push((addr, value));
+    }
+
+    pub fn clear_pokes(&mut self) {
+        self.pokes.clear();
+    }
+
+    fn run_frame(&mut self, timers: bool) -> Result<FrameOutcome, String> {
+        if let Some(log) = &mut self.input_log {
+            if log.last().map(|&(_, keypad)| keypad) != Some(self.keypad) {
+                log.push((self.frame_count, self.keypad));
+            }
+        }
+        self.frame_count += 1;
+        self.drew_this_frame = false;
+        if timers && !self.freeze_timers {
+            self.decrement_timers();
+        }
+        for idx in 0..self.pokes.len() {
+            let (addr, value) = self.pokes[idx];
+            self.write_mem(addr as usize, value)?;
+        }
+        match self.instructions_per_frame {
+            Some(count) => self.run_instructions(count)?,
+            None => {
+                self.run_cycles(self.cycles_per_frame)?;
+            }
+        }
+        let idle = self.detect_halt_loop && (self.self_jump || self.track_idle_frames());
+        return Ok(if self.halted {
+            FrameOutcome::Halted
+        } else if self.waiting_for_key {
+            FrameOutcome::WaitingForKey
+        } else if idle {
+            FrameOutcome::Idle
+        } else {
+            FrameOutcome::Completed
+        });
+    }
+
+    // Stops frame from counting the timers down, only decrement_timers does
+    pub fn set_freeze_timers(&mut self, frozen: bool) {
+        self.freeze_timers = frozen;
+    }
+
+    // Makes step error on an instruction cost of 0 or over MAX_OP_CYCLES, a
+    // cost table slip that would otherwise hang run_cycles or stall frames.
+    // Fx0A and display waits spend the rest of the frame and 00FD halts, so
+    // those are left alone
+    pub fn set_check_cycle_costs(&mut self, enabled: bool) {
+        self.check_cycle_costs = enabled;
+    }
+
+    // Makes frame report Idle when the rom jumps to itself, or when the
+    // display stayed the same with no timer running and no key down for the
+    // idle frame threshold. A self-jump also ends the frame early
+    pub fn set_detect_halt_loop(&mut self, enabled: bool) {
+        self.detect_halt_loop = enabled;
+        self.unchanged_frames = 0;
+    }
+
+    pub fn set_idle_frame_threshold(&mut self, frames: u64) {
+        self.idle_frame_threshold = frames;
+    }
+
+    // Whether the frame that just ran pushes the unchanged frame count over
+    // the threshold
+    fn track_idle_frames(&mut self) -> bool {
+        let hash = self.display_hash();
+        let quiet = self.delay_timer == 0 && self.sound_timer == 0 && self.keypad == 0;
+        if quiet && hash == self.last_display_hash {
+            self.unchanged_frames += 1;
+        } else {
+            self.unchanged_frames = 0;
+        }
+        self.last_display_hash = hash;
+        return self.unchanged_frames >= self.idle_frame_threshold;
+    }
+
+    // Whether the last instruction was an Fx0A still waiting for a key. It
+    // completes as soon as any key is down, not once it is released
+    pub fn is_waiting_for_key(&self) -> bool {
+        return self.waiting_for_key;
+    }
+
+    // Stops step_n before the instruction at addr
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn at_breakpoint(&self) -> bool {
+        return self.breakpoints.contains(&self.pc);
+    }
+
+    // Executes n instructions, returning the cycles spent. Stops early when
+    // the machine halts or reaches a breakpoint, check at_breakpoint to tell.
+    // A breakpoint at the starting pc does not stop it so it can be stepped
+    // off of
+    pub fn step_n(&mut self, n: usize) -> Result<usize, String> {
+        let mut spent = 0;
+        for idx in 0..n {
+            if self.halted || (idx > 0 && self.at_breakpoint()) {
+                break;
+            }
+            spent += self.tick()?;
+        }
+        return Ok(spent);
+    }
+
+    // Executes instructions until budget cycles are spent, without touching
+    // the timers. Returns the cycles actually spent, which overshoots budget
+    // by up to one instruction or falls short if the machine halts
+    pub fn run_cycles(&mut self, budget: usize) -> Result<usize, String> {
+        let mut spent = 0;
+        while spent < budget && !self.halted {
+            spent += self.frame_tick()?;
+            if self.self_jump && self.detect_halt_loop {
+                break;
+            }
+        }
+        return Ok(spent);
+    }
+
+    // Frame loop for instructions_per_frame, cycle costs are ignored. A
+    // pending Fx0A or display wait ends the frame like it does in a cycle
+    // budget
+    fn run_instructions(&mut self, count: usize) -> Result<(), String> {
+        for _ in 0..count {
+            if self.halted {
+                break;
+            }
+            self.frame_tick()?;
+            let blocked = self.waiting_for_key || self.waiting_for_display;
+            if blocked || (self.self_jump && self.detect_halt_loop) {
+                break;
+            }
+        }
+        return Ok(());
+    }
+
+    // tick, skipping failed instructions under SkipAsNop
+    fn frame_tick(&mut self) -> Result<usize, String> {
+        let pc = self.pc;
+        return match self.tick() {
+            // pc stays put when the fetch itself failed, skipping that would
+            // spin forever
+            Err(err) if self.invalid_op_policy == InvalidOpPolicy::SkipAsNop && self.pc != pc => {
+                self.diagnostics.push(format!("0x{:03x}: {}", pc, err));
+                Ok(INVALID_OP_DURATION)
+            }
+            result => result,
+        };
+    }
+
+    // Runs frames back to back without any real-time pacing
+    pub fn run_frames(&mut self, frames: u64) -> Result<(), String> {
+        for _ in 0..frames {
+            self.frame()?;
+        }
+        return Ok(());
+    }
+
+    // The part of the display buffer the current resolution uses
+    pub fn display_buffer(&self) -> &[u8] {
+        return &self.display[..self.display_width() * self.display_height() / 8];
+    }
+
+    // Whether 00FF switched to the 128x64 SUPER-CHIP display
+    pub fn is_hires(&self) -> bool {
+        return self.hires;
+    }
+
+    pub fn display_width(&self) -> usize {
+        return if self.hires { HIRES_WIDTH } else { DISPLAY_WIDTH };
+    }
+
+    pub fn display_height(&self) -> usize {
+        return if self.hires { HIRES_HEIGHT } else { DISPLAY_HEIGHT };
+    }
+
+    // FNV-1a of the framebuffer. Unlike the std hasher it gives the same value
+    // on every platform, run and crate version, so golden values stay valid
+    pub fn display_hash(&self) -> u64 {
+        // plane2 only counts once it is drawn to, a CHIP-8 hash stays the same
+        if self.plane2.iter().all(|&byte| byte == 0) {
+            return fnv1a(self.display_buffer());
+        }
+        return fnv1a(&[self.display_buffer(), self.plane2_buffer()].concat());
+    }
+
+    // Blanks the display like 00E0, without running an opcode or touching vf
+    // and the display wait. Both XO-CHIP planes are cleared whichever are
+    // selected
+    pub fn clear_display(&mut self) {
+        self.display.fill(0);
+        self.plane2.fill(0);
+    }
+
+    // The second XO-CHIP plane, laid out like display_buffer. Only Dxyn
+    // under Fn01 with bit 1 set draws to it
+    pub fn plane2_buffer(&self) -> &[u8] {
+        return &self.plane2[..self.display_width() * self.display_height() / 8];
+    }
+
+    // Pixel (x, y) as an XO-CHIP color, bit 0 from the display and bit 1
+    // from plane2
+    pub fn pixel_color(&self, x: usize, y: usize) -> u8 {
+        let idx = pixel_byte_index(x, y, self.display_width());
+        let bit = 7 - x % 8;
+        return (self.display[idx] >> bit & 1) | (self.plane2[idx] >> bit & 1) << 1;
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        return self.display[pixel_byte_index(x, y, self.display_width())] >> (7 - x % 8) & 1 != 0;
+    }
+
+    // The framebuffer as a plain PBM (P1) image, 1 being a lit pixel. Rows
+    // have no separators and hires ones are wrapped so lines stay under the
+    // format's 70 character limit
+    pub fn display_pbm(&self) -> String {
+        let mut pbm = format!("P1\n{} {}\n", self.display_width(), self.display_height());
+        for y in 0..self.display_height() {
+            for x in 0..self.display_width() {
+                if x > 0 && x % PBM_LINE_WIDTH == 0 {
+                    pbm.push('\n');
+                }
+                pbm.push(if self.pixel(x, y) { '1' } else { '0' });
+            }
+            pbm.push('\n');
+        }
+        return pbm;
+    }
+
+    // Executes the instruction at pc, without touching the timers
+    pub fn tick(&mut self) -> Result<usize, String> {
+        if self.halted {
+            return Ok(0);
+        }
+        let (op0, op1) = self.peek_instruction()?;
+        // An instruction at 0xFFFE under XO-CHIP would leave pc past what
+        // fits in 16 bits, the 4K equivalent errors on the next fetch instead
+        if self.pc.checked_add(2).is_none() {
+            return Err("pc out of memory bounds".to_string());
+        }
+        if let Some(hook) = &mut self.trace_hook {
+            hook(self.pc, op0, op1);
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(
+            "instruction",
+            pc = %format_args!("0x{:03x}", self.pc),
+            opcode = %format_args!("{:04x}", opcode!(op0, op1)),
+        )
+        .entered();
+        let result = if self.rewind_capacity == 0 {
+            self.pc += 2;
+            self.step(op0, op1)
+        } else {
+            self.step_recorded(op0, op1)
+        };
+        #[cfg(feature = "tracing")]
+        if let Ok(cycles) = result {
+            tracing::trace!(cycles);
+        }
+        return result;
+    }
+
+    // step, keeping a snapshot of what it changes for step_back
+    fn step_recorded(&mut self, op0: u8, op1: u8) -> Result<usize, String> {
+        let mut snapshot = Snapshot {
+            pc: self.pc,
+            i: self.i,
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            registers: self.registers,
+            display: self.display,
+            plane2: self.plane2,
+            planes: self.planes,
+            hires: self.hires,
+            halted: self.halted,
+            waiting_for_key: self.waiting_for_key,
+            waiting_for_display: self.waiting_for_display,
+            pitch: self.pitch,
+            audio_pattern: self.audio_pattern,
+            rpl_flags: self.rpl_flags,
+            memory_writes: vec![],
+        };
+        self.pending_writes.clear();
+        self.pc += 2;
+        let result = self.step(op0, op1);
+        snapshot.memory_writes = std::mem::take(&mut self.pending_writes);
+        if self.history.len() == self.rewind_capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(snapshot);
+        return result;
+    }
+
+    // Fuzz target, runs one opcode against whatever state the caller set up.
+    // Any input has to come back as Ok or Err, never as a panic
+    pub fn execute_raw(&mut self, op0: u8, op1: u8) -> Result<usize, String> {
+        return self.step(op0, op1);
+    }
+
+    // The two opcode bytes at pc. pc is never masked to 12 bits: running off
+    // the end of memory, e.g. past an instruction at 0xFFE, errors here on
+    // the next fetch rather than wrapping to 0x000
+    pub fn peek_instruction(&self) -> Result<(u8, u8), String> {
+        if self.pc as usize >= self.memory_size() - 1 {
+            return Err("pc out of memory bounds".to_string());
+        }
+        return Ok((self.memory[self.pc as usize], self.memory[(self.pc + 1) as usize]));
+    }
+
+    // The full opcode at pc, high byte first
+    pub fn current_opcode(&self) -> Result<u16, String> {
+        let (op0, op1) = self.peek_instruction()?;
+        return Ok(opcode!(op0, op1));
+    }
+
+    pub fn peek_decoded(&self) -> Option<Instruction> {
+        let (op0, op1) = self.peek_instruction().ok()?;
+        return Instruction::decode(op0, op1);
+    }
+
+    pub fn step(&mut self, op0: u8, op1: u8) -> Result<usize, String> {
+        let cycles = self.execute(op0, op1)?;
+        let waiting = self.waiting_for_key || self.waiting_for_display || self.halted;
+        if self.check_cycle_costs && !waiting && !(1..=MAX_OP_CYCLES).contains(&cycles) {
+            return Err(format!("op {:04x} cost {} cycles", opcode!(op0, op1), cycles));
+        }
+        return Ok(cycles);
+    }
+
+    fn execute(&mut self, op0: u8, op1: u8) -> Result<usize, String> {
+        // println!("0x{:x}{:x}{:x}{:x}", hi!(op0), lo!(op0), hi!(op1), lo!(op1));
+        self.instruction_count += 1;
+        self.waiting_for_key = false;
+        self.waiting_for_display = false;
+        self.self_jump = false;
+        if self.profiling {
+            *self.opcode_histogram.entry(opcode!(op0, op1)).or_insert(0) += 1;
+        }
+        let instruction = match Instruction::decode(op0, op1) {
+            Some(instruction) => instruction,
+            None => return self.invalid_op(op0, op1),
+        };
+        let xochip_only = matches!(instruction, Instruction::LdILong | Instruction::Plane(_) | Instruction::Audio);
+        if xochip_only && !self.xochip {
+            return self.invalid_op(op0, op1);
+        }
+        // decode ignores the last nibble of 5xy0 and 9xy0
+        let unused_nibble = matches!(instruction, Instruction::SeXy(..) | Instruction::SneXy(..));
+        if self.strict_opcodes && unused_nibble && lo!(op1) != 0 {
+            return self.invalid_op(op0, op1);
+        }
+        return Ok(match instruction {
+            // 00e0
+            Instruction::Cls => self.op_cls(),
+            // 00ee
+            Instruction::Ret => self.op_ret()?,
+            // 00cn
+            Instruction::ScrollDown(n) => self.op_scd(n),
+            // 00fb
+            Instruction::ScrollRight => self.op_scr(),
+            // 00fc
+            Instruction::ScrollLeft => self.op_scl(),
+            // 00fd
+            Instruction::Exit => self.op_exit(),
+            // 00fe
+            Instruction::Low => self.op_low(),
+            // 00ff
+            Instruction::High => self.op_high(),
+            // 1nnn
+            Instruction::Jp(addr) => self.op_jp(addr)?,
+            // 2nnn
+            Instruction::Call(addr) => self.op_call(addr)?,
+            // 3xnn
+            Instruction::Se(x, byte) => self.op_se(x, byte),
+            // 4xnn
+            Instruction::Sne(x, byte) => self.op_sne(x, byte),
+            // 5xy0
+            Instruction::SeXy(x, y) => self.op_sexy(x, y),
+            // 6xnn
+            Instruction::Ld(x, byte) => self.op_ld(x, byte),
+            // 7xnn
+            Instruction::Add(x, byte) => self.op_add(x, byte),
+            // 8xy0
+            Instruction::LdXy(x, y) => self.op_ldxy(x, y),
+            // 8xy1
+            Instruction::OrXy(x, y) => self.op_orxy(x, y),
+            // 8xy2
+            Instruction::AndXy(x, y) => self.op_andxy(x, y),
+            // 8xy3
+            Instruction::XorXy(x, y) => self.op_xorxy(x, y),
+            // 8xy4
+            Instruction::AddXy(x, y) => self.op_addxy(x, y),
+            // 8xy5
+            Instruction::SubXy(x, y) => self.op_subxy(x, y),
+            // 8xy6
+            Instruction::ShrXy(x, y) => self.op_shrxy(x, y),
+            // 8xy7
+            Instruction::SubnXy(x, y) => self.op_subnxy(x, y),
+            // 8xyE
+            Instruction::ShlXy(x, y) => self.op_shlxy(x, y),
+            // 9xy0
+            Instruction::SneXy(x, y) => self.op_snexy(x, y),
+            // Annn
+            Instruction::LdI(addr) => self.op_ldi(addr),
+            // Bnnn
+            Instruction::JpV0(addr) => self.op_jp0(addr)?,
+            // Cxkk
+            Instruction::Rnd(x, byte) => self.op_rndx(x, byte),
+            // Dxyn
+            Instruction::Drw(x, y, nibble) => self.op_drw(x, y, nibble)?,
+            // Ex9E
+            Instruction::Skp(x) => self.op_skpx(x),
+            // ExA1
+            Instruction::Sknp(x) => self.op_sknpx(x),
+            // F000 nnnn
+            Instruction::LdILong => self.op_ldilong()?,
+            // Fn01
+            Instruction::Plane(n) => self.op_plane(n),
+            // F002
+            Instruction::Audio => self.op_audio()?,
+            // Fx07
+            Instruction::LdXDt(x) => self.op_ldxdt(x),
+            // Fx0A
+            Instruction::LdXK(x) => self.op_ldxk(x),
+            // Fx15
+            Instruction::LdDtX(x) => self.op_lddtx(x),
+            // Fx18
+            Instruction::LdStX(x) => self.op_ldstx(x),
+            // Fx1E
+            Instruction::AddIX(x) => self.op_addix(x),
+            // Fx29
+            Instruction::LdFX(x) => self.op_ldfx(x),
+            // Fx30
+            Instruction::LdHfX(x) => self.op_ldhfx(x),
+            // Fx33
+            Instruction::LdBX(x) => self.op_ldbx(x)?,
+            // Fx3A
+            Instruction::PitchX(x) => self.op_pitchx(x),
+            // Fx55
+            Instruction::LdIX(x) => self.op_ldix(x)?,
+            // Fx65
+            Instruction::LdXI(x) => self.op_ldxi(x)?,
+            // Fx75
+            Instruction::LdRX(x) => self.op_ldrx(x)?,
+            // Fx85
+            Instruction::LdXR(x) => self.op_ldxr(x)?,
+        });
+    }
+
+    fn write_mem(&mut self, addr: usize, value: u8) -> Result<(), String> {
+        if addr >= self.memory_size() {
+            return Err(format!("memory write out of bounds at 0x{:x}", addr));
+        }
+        if addr < RESERVED_MEMORY_SIZE {
+            match self.reserved_write_policy {
+                ReservedWritePolicy::Allow => {}
+                ReservedWritePolicy::Error => {
+                    return Err(format!("write to reserved memory at 0x{:03x}", addr));
+                }
+                ReservedWritePolicy::Ignore => return Ok(()),
+            }
+        }
+        if self.rewind_capacity != 0 {
+            self.pending_writes.push((addr, self.memory[addr]));
+        }
+        self.memory[addr] = value;
+        return Ok(());
+    }
+
+    // A jump target must leave room to fetch a whole instruction
+    fn check_jump(&self, mnemonic: &str, addr: u16) -> Result<(), String> {
+        if addr as usize >= self.memory_size() - 1 {
+            return Err(format!("{} to out-of-bounds address 0x{:03x}", mnemonic, addr));
+        }
+        return Ok(());
+    }
+
+    // The count bytes from i must fit in memory unless i is allowed to wrap
+    fn check_i_range(&self, count: usize) -> Result<(), String> {
+        if count == 0 {
+            return Ok(());
+        }
+        let last = self.i as usize + count - 1;
+        if !self.quirks.wrap_i && last >= self.memory_size() {
+            return Err(format!("i range 0x{:x}..=0x{:x} out of memory bounds", self.i, last));
+        }
+        return Ok(());
+    }
+
+    fn halt(&mut self) {
+        if self.halted {
+            return;
+        }
+        self.halted = true;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(pc = %format_args!("0x{:03x}", self.pc), "halted");
+        if let Some(hook) = &mut self.halt_hook {
+            hook();
+        }
+    }
+
+    fn invalid_op(&mut self, op0: u8, op1: u8) -> Result<usize, String> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            pc = %format_args!("0x{:03x}", self.pc.wrapping_sub(2)),
+            opcode = %format_args!("{:04x}", opcode!(op0, op1)),
+            "invalid op"
+        );
+        if let Some(handler) = &mut self.custom_opcode_handler {
+            let mut context = Chip8Context {
+                memory: &mut self.memory,
+                pc: &mut self.pc,
+                i: &mut self.i,
+                stack: &mut self.stack,
+                delay_timer: &mut self.delay_timer,
+                sound_timer: &mut self.sound_timer,
+                registers: &mut self.registers,
+                display: &mut self.display,
+                keypad: self.keypad,
+            };
+            if let Some(cycles) = handler(&mut context, op0, op1) {
+                return Ok(cycles);
+            }
+        }
+        return match self.invalid_op_policy {
+            InvalidOpPolicy::Error => {
+                Err(format!("Invalid op {:04x}", opcode!(op0, op1)))
+            }
+            InvalidOpPolicy::SkipAsNop => {
+                let pc = self.pc.wrapping_sub(2);
+                let op = opcode!(op0, op1);
+                self.diagnostics.push(format!("0x{:03x}: skipped invalid op {:04x}", pc, op));
+                Ok(INVALID_OP_DURATION)
+            }
+            InvalidOpPolicy::Halt => {
+                self.halt();
+                Ok(INVALID_OP_DURATION)
+            }
+        };
+    }
+
+    // 00e0
+    fn op_cls(&mut self) -> usize {
+        if self.quirks.cls_waits && self.wait_for_display() {
+            return self.cycles_per_frame;
+        }
+        if self.planes & 1 != 0 {
+            self.display.fill(0);
+        }
+        if self.planes & 2 != 0 {
+            self.plane2.fill(0);
+        }
+        if self.quirks.cls_resets_vf {
+            self.registers[0xf] = 0;
+        }
+        return 109;
+    }
+
+    // Under display_wait the display changes once a frame, whatever changes
+    // it after that goes back to pc and ends the frame, to run again in the
+    // next one. Returns whether the caller has to wait
+    fn wait_for_display(&mut self) -> bool {
+        if !self.quirks.display_wait {
+            return false;
+        }
+        if self.drew_this_frame {
+            self.pc = self.pc.wrapping_sub(2);
+            self.waiting_for_display = true;
+            return true;
+        }
+        self.drew_this_frame = true;
+        return false;
+    }
+
+    // 00ee
+    fn op_ret(&mut self) -> Result<usize, String> {
+        let addr = match self.stack.pop() {
+            Some(addr) => addr,
+            None => return Err("stack underflow".to_string()),
+        };
+        self.pc = addr;
+        return Ok(105);
+    }
+
+    // 00fd
+    fn op_exit(&mut self) -> usize {
+        self.halt();
+        return 0;
+    }
+
+    // 00cn, n rows of the current resolution
+    fn op_scd(&mut self, n: u8) -> usize {
+        let row = self.display_width() / 8;
+        let len = self.display_buffer().len();
+        let shift = (n as usize * row).min(len);
+        for plane in 0..2 {
+            if self.planes >> plane & 1 != 0 {
+                let buffer = &mut self.plane_mut(plane)[..len];
+                buffer.copy_within(..len - shift, shift);
+                buffer[..shift].fill(0);
+            }
+        }
+        return 109;
+    }
+
+    // 00fb
+    fn op_scr(&mut self) -> usize {
+        self.scroll_horizontal(false);
+        return 109;
+    }
+
+    // 00fc
+    fn op_scl(&mut self) -> usize {
+        self.scroll_horizontal(true);
+        return 109;
+    }
+
+    // Shifts every row of the selected planes 4 pixels, half a byte, so each
+    // byte takes a nibble from its neighbour. Pixels are not wrapped around
+    fn scroll_horizontal(&mut self, left: bool) {
+        let row = self.display_width() / 8;
+        let len = self.display_buffer().len();
+        for plane in 0..2 {
+            if self.planes >> plane & 1 == 0 {
+                continue;
+            }
+            for bytes in self.plane_mut(plane)[..len].chunks_exact_mut(row) {
+                if left {
+                    for idx in 0..row {
+                        let next = if idx + 1 < row { bytes[idx + 1] } else { 0 };
+                        bytes[idx] = bytes[idx] << 4 | next >> 4;
+                    }
+                } else {
+                    for idx in (0..row).rev() {
+                        let prev = if idx > 0 { bytes[idx - 1] } else { 0 };
+                        bytes[idx] = bytes[idx] >> 4 | prev << 4;
+                    }
+                }
+            }
+        }
+    }
+
+    // 00fe
+    fn op_low(&mut self) -> usize {
+        self.set_hires(false);
+        return 109;
+    }
+
+    // 00ff
+    fn op_high(&mut self) -> usize {
+        self.set_hires(true);
+        return 109;
+    }
+
+    // Rows are packed at the width, so whatever was drawn would come out
+    // scrambled in the other resolution. Like Octo, switching clears
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.clear_display();
+    }
+
+    // 1nnn
+    fn op_jp(&mut self, addr: u16) -> Result<usize, String> {
+        self.check_jump("jp", addr)?;
+        // pc already points past this instruction
+        self.self_jump = addr == self.pc.wrapping_sub(2);
+        self.pc = addr;
+        return Ok(105);
+    }
+
+    // 2nnn
+    fn op_call(&mut self, addr: u16) -> Result<usize, String> {
+        if self.max_stack_depth.is_some_and(|depth| self.stack.len() >= depth) {
+            return Err("stack overflow".to_string());
+        }
+        self.check_jump("call", addr)?;
+        self.stack.push(self.pc);
+        self.pc = addr;
+        return Ok(105);
+    }
+
+    // 3xnn
+    fn op_se(&mut self, vx: u8, byte: u8) -> usize {
+        if self.registers[vx as usize] == byte {
+            self.skip_next();
+            return 64;
+        }
+        return 46;
+    }
+
+    // 4xnn
+    fn op_sne(&mut self, vx: u8, byte: u8) -> usize {
+        if self.registers[vx as usize] != byte {
+            self.skip_next();
+            return 64;
+        }
+        return 46;
+    }
+
+    // 5xy0
+    fn op_sexy(&mut self, vx: u8, vy: u8) -> usize {
+        if self.registers[vx as usize] == self.registers[vy as usize] {
+            self.skip_next();
+            return 82;
+        }
+        return 64;
+    }
+
+    // 6xnn
+    fn op_ld(&mut self, vx: u8, byte: u8) -> usize {
+        self.registers[vx as usize] = byte;
+        return 27;
+    }
+
+    // 7xnn
+    fn op_add(&mut self, vx: u8, byte: u8) -> usize {
+        self.registers[vx as usize] = self.registers[vx as usize].wrapping_add(byte);
+        return 45;
+    }
+
+    // 8xy0
+    fn op_ldxy(&mut self, vx: u8, vy: u8) -> usize {
+        self.registers[vx as usize] = self.registers[vy as usize];
+        return 200;
+    }
+
+    // 8xy1
+    fn op_orxy(&mut self, vx: u8, vy: u8) -> usize {
+        self.registers[vx as usize] |= self.registers[vy as usize];
+        if self.quirks.vf_reset {
+            self.registers[0xf] = 0;
+        }
+        return 200;
+    }
+
+    // 8xy2
+    fn op_andxy(&mut self, vx: u8, vy: u8) -> usize {
+        self.registers[vx as usize] &= self.registers[vy as usize];
+        if self.quirks.vf_reset {
+            self.registers[0xf] = 0;
+        }
+        return 200;
+    }
+
+    // 8xy3
+    fn op_xorxy(&mut self, vx: u8, vy: u8) -> usize {
+        self.registers[vx as usize] ^= self.registers[vy as usize];
+        if self.quirks.vf_reset {
+            self.registers[0xf] = 0;
+        }
+        return 200;
+    }
+
+    // 8xy4
+    fn op_addxy(&mut self, vx: u8, vy: u8) -> usize {
+        let (result, overflows) = self.registers[vx as usize]
+            .overflowing_add(self.registers[vy as usize]);
+        self.registers[vx as usize] = result;
+        self.registers[0xf] = if overflows { 1 } else { 0 };
+        return 200;
+    }
+
+    // 8xy5
+    fn op_subxy(&mut self, vx: u8, vy: u8) -> usize {
+        let (result, overflows) = self.registers[vx as usize]
+            .overflowing_sub(self.registers[vy as usize]);
+        self.registers[vx as usize] = result;
+        self.registers[0xf] = if overflows { 0 } else { 1 };
+        return 200;
+    }
+
+    // 8xy6
+    fn op_shrxy(&mut self, vx: u8, vy: u8) -> usize {
+        let src = if self.quirks.shift_vy { vy } else { vx };
+        let x = self.registers[src as usize];
+        self.registers[vx as usize] = x >> 1;
+        self.registers[0xf] = x & 0b00000001;
+        return 200;
+    }
+
+    // 8xy7
+    fn op_subnxy(&mut self, vx: u8, vy: u8) -> usize {
+        let (result, overflows) = self.registers[vy as usize]
+            .overflowing_sub(self.registers[vx as usize]);
+        self.registers[vx as usize] = result;
+        self.registers[0xf] = if overflows { 0 } else { 1 };
+        return 200;
+    }
+
+    // 8xyE
+    fn op_shlxy(&mut self, vx: u8, vy: u8) -> usize {
+        let src = if self.quirks.shift_vy { vy } else { vx };
+        let x = self.registers[src as usize];
+        // Bits shifted past bit 7 are dropped, only the shift amount can overflow
+        self.registers[vx as usize] = x << 1;
+        self.registers[0xf] = (x & 0b10000000) >> 7;
+        return 200;
+    }
+
+    // 9xy0
+    fn op_snexy(&mut self, vx: u8, vy: u8) -> usize {
+        if self.registers[vx as usize] != self.registers[vy as usize] {
+            self.skip_next();
+            return 82;
+        }
+        return 64;
+    }
+
+    // Skips the instruction at pc, F000 nnnn being 4 bytes long on XO-CHIP
+    fn skip_next(&mut self) {
+        let pc = self.pc as usize;
+        let long = self.xochip
+            && pc + 1 < self.memory_size()
+            && self.memory[pc..pc + 2] == [0xF0, 0x00];
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
+    }
+
+    // Annn
+    fn op_ldi(&mut self, addr: u16) -> usize {
+        self.i = addr;
+        return 55;
+    }
+
+    // Bnnn
+    fn op_jp0(&mut self, addr: u16) -> Result<usize, String> {
+        let offset_register = if self.quirks.jump_vx { (addr >> 8) as usize } else { 0x0 };
+        let target = addr + self.registers[offset_register] as u16;
+        self.check_jump("jp", target)?;
+        self.pc = target;
+        return Ok(105);
+    }
+
+    // Cxkk
+    fn op_rndx(&mut self, vx: u8, byte: u8) -> usize {
+        let r: u8 = match self.rng_constant {
+            Some(value) => value,
+            None => self.rng.gen(),
+        };
+        self.last_random = Some(r);
+        self.registers[vx as usize] = r & byte;
+        return 164;
+    }
+
+    // Dxyn
+    fn op_drw(&mut self, vx: u8, vy: u8, nibble: u8) -> Result<usize, String> {
+        let (rows, row_bytes) = match nibble {
+            // A 16x16 sprite, two bytes a row, in SUPER-CHIP hires mode and
+            // always under XO-CHIP. On the CHIP-8 display it draws nothing
+            // and clears vf
+            0 if self.hires || self.xochip => (16, 2),
+            0 => (0, 1),
+            // Wide sprites are 16 pixels across too
+            n if self.wide_sprites => (n as usize, 2),
+            n => (n as usize, 1),
+        };
+        // Every selected XO-CHIP plane takes its own sprite, one after the
+        // other from i
+        let sprite_size = rows * row_bytes;
+        let planes: Vec<usize> = (0..2).filter(|plane| self.planes >> plane & 1 != 0).collect();
+        self.check_i_range(sprite_size * planes.len())?;
+        if self.wait_for_display() {
+            return Ok(self.cycles_per_frame);
+        }
+        let x = self.registers[vx as usize];
+        let y = self.registers[vy as usize];
+        let (width, height) = (self.display_width(), self.display_height());
+        let display_x = x as usize % width;
+        let shift = x % 8;
+        let columns = width / 8;
+        let mut prev: u8 = 0;
+
+        for (sprite, &plane) in planes.iter().enumerate() {
+            let start = self.i as usize + sprite * sprite_size;
+            for idx in 0..rows {
+                if !self.quirks.wrap_y && (y as usize % height) + idx >= height {
+                    break;
+                }
+                let display_y = (y as usize + idx) % height;
+                let row = display_y * columns;
+                for offset in 0..row_bytes {
+                    // Unwrapped columns, past the right edge they either wrap
+                    // or get clipped
+                    let left = display_x / 8 + offset;
+                    let right = left + 1;
+                    if !self.quirks.wrap_x && left >= columns {
+                        break;
+                    }
+                    let byte = self.memory[(start + idx * row_bytes + offset) % self.memory_size()];
+
+                    let display_column_left = left % columns;
+                    let shifted_left = byte >> shift;
+                    let prev_left = &mut self.plane_mut(plane)[row + display_column_left];
+                    let hit_left = *prev_left & shifted_left;
+                    prev |= hit_left;
+                    *prev_left ^= shifted_left;
+                    self.log_collisions(display_column_left, display_y, hit_left);
+
+                    if shift > 0 && (self.quirks.wrap_x || right < columns) {
+                        let display_column_right = right % columns;
+                        let shifted_right = byte << (8 - shift);
+                        let prev_right = &mut self.plane_mut(plane)[row + display_column_right];
+                        let hit_right = *prev_right & shifted_right;
+                        prev |= hit_right;
+                        *prev_right ^= shifted_right;
+                        self.log_collisions(display_column_right, display_y, hit_right);
+                    }
+                }
+            }
+        }
+        self.registers[0xf] = if prev != 0 { 1 } else { 0 };
+        if prev != 0 {
+            self.collision_count += 1;
+        }
+        if let Some(events) = &mut self.draw_events {
+            events.push(DrawEvent {
+                // pc has already moved past the Dxyn
+                pc: self.pc.wrapping_sub(2),
+                x,
+                y,
+                height: nibble,
+                collided: prev != 0,
+            });
+        }
+        return Ok(22734);
+    }
+
+    fn plane_mut(&mut self, plane: usize) -> &mut [u8; DISPLAY_SIZE] {
+        return if plane == 0 { &mut self.display } else { &mut self.plane2 };
+    }
+
+    // hits being the bits of display byte column on row y that a draw
+    // turned off
+    fn log_collisions(&mut self, column: usize, y: usize, hits: u8) {
+        if let Some(pixels) = &mut self.collision_pixels {
+            for bit in (0..8).rev() {
+                if hits >> bit & 1 != 0 {
+                    pixels.push((column * 8 + 7 - bit, y));
+                }
+            }
+        }
+    }
+
+    // Ex9E
+    fn op_skpx(&mut self, vx: u8) -> usize {
+        // Only the low nibble names a key, larger values would overflow the shift
+        let x = self.registers[vx as usize] & 0x0f;
+        if self.keypad & (1u16 << x) != 0 {
+            self.skip_next();
+            return 64;
+        }
+        return 82;
+    }
+
+    // ExA1
+    fn op_sknpx(&mut self, vx: u8) -> usize {
+        let x = self.registers[vx as usize] & 0x0f;
+        if self.keypad & (1u16 << x) == 0 {
+            self.skip_next();
+            return 64;
+        }
+        return 82;
+    }
+
+    // F000 nnnn
+    fn op_ldilong(&mut self) -> Result<usize, String> {
+        let pc = self.pc as usize;
+        if pc + 1 >= self.memory_size() {
+            return Err("F000 address past the end of memory".to_string());
+        }
+        self.i = u16::from_be_bytes([self.memory[pc], self.memory[pc + 1]]);
+        self.pc = self.pc.wrapping_add(2);
+        return Ok(110);
+    }
+
+    // Fn01, only the low two bits name planes
+    fn op_plane(&mut self, n: u8) -> usize {
+        self.planes = n & 0b11;
+        return 45;
+    }
+
+    // Fx07
+    fn op_ldxdt(&mut self, vx: u8) -> usize {
+        self.registers[vx as usize] = self.delay_timer;
+        return 45;
+    }
+
+    // Fx0A
+    fn op_ldxk(&mut self, vx: u8) -> usize {
+        for i in 0..16 {
+            if 1 << i & self.keypad != 0 {
+                self.registers[vx as usize] = i as u8;
+                return 200;
+            }
+        }
+        self.pc = self.pc.wrapping_sub(2);
+        self.waiting_for_key = true;
+        return self.cycles_per_frame;
+    }
+
+    // Fx15
+    fn op_lddtx(&mut self, vx: u8) -> usize {
+        self.delay_timer = self.registers[vx as usize];
+        return 45;
+    }
+
+    // Fx18
+    fn op_ldstx(&mut self, vx: u8) -> usize {
+        self.sound_timer = self.registers[vx as usize];
+        return 45;
+    }
+
+    // Fx1e
+    fn op_addix(&mut self, vx: u8) -> usize {
+        let x = self.registers[vx as usize];
+        let sum = self.i as u32 + x as u32;
+        self.i = self.i.wrapping_add(x as u16);
+        if self.quirks.fx1e_sets_vf {
+            self.registers[0xf] = if sum > 0x0FFF { 1 } else { 0 };
+        }
+        return 86;
+    }
+
+    // Fx29
+    fn op_ldfx(&mut self, vx: u8) -> usize {
+        let x = self.registers[vx as usize];
+        self.i = FONT_ADDRESS as u16 + (x as u16) * 5;
+        return 91;
+    }
+
+    // Fx30, only the low nibble picks a digit so i stays inside the font
+    fn op_ldhfx(&mut self, vx: u8) -> usize {
+        let x = self.registers[vx as usize] & 0xf;
+        self.i = BIG_FONT_ADDRESS as u16 + (x as u16) * 10;
+        return 91;
+    }
+
+    // Fx33
+    fn op_ldbx(&mut self, vx: u8) -> Result<usize, String> {
+        let x = self.registers[vx as usize];
+        self.write_mem(self.i as usize, x / 100)?;
+        self.write_mem(self.i as usize + 1, x / 10 % 10)?;
+        self.write_mem(self.i as usize + 2, x % 10)?;
+        return Ok(bcd_cycles(x));
+    }
+
+    // F002
+    fn op_audio(&mut self) -> Result<usize, String> {
+        self.check_i_range(AUDIO_PATTERN_SIZE)?;
+        let mut pattern = [0; AUDIO_PATTERN_SIZE];
+        for (idx, byte) in pattern.iter_mut().enumerate() {
+            *byte = self.memory[(idx + self.i as usize) % self.memory_size()];
+        }
+        self.audio_pattern = Some(pattern);
+        return Ok(bulk_transfer_cycles(AUDIO_PATTERN_SIZE as u8 - 1));
+    }
+
+    // Fx3A
+    fn op_pitchx(&mut self, vx: u8) -> usize {
+        self.pitch = Some(self.registers[vx as usize]);
+        return 45;
+    }
+
+    // Fx55
+    fn op_ldix(&mut self, vx: u8) -> Result<usize, String> {
+        self.check_i_range(vx as usize + 1)?;
+        for i in 0..vx as usize + 1 {
+            let v = self.registers[i];
+            self.write_mem((i + self.i as usize) % self.memory_size(), v)?;
+        }
+        if self.quirks.increment_i {
+            self.i = self.i.wrapping_add(vx as u16 + 1);
+        }
+        return Ok(bulk_transfer_cycles(vx));
+    }
+
+    // Fx65
+    fn op_ldxi(&mut self, vx: u8) -> Result<usize, String> {
+        self.check_i_range(vx as usize + 1)?;
+        for i in 0..vx as usize + 1 {
+            self.registers[i] = self.memory[(i + self.i as usize) % self.memory_size()];
+        }
+        if self.quirks.increment_i {
+            self.i = self.i.wrapping_add(vx as u16 + 1);
+        }
+        return Ok(bulk_transfer_cycles(vx));
+    }
+
+    // Fx75
+    fn op_ldrx(&mut self, vx: u8) -> Result<usize, String> {
+        self.check_rpl_range(vx)?;
+        let count = vx as usize + 1;
+        self.rpl_flags[..count].copy_from_slice(&self.registers[..count]);
+        if let Some(hook) = &mut self.flags_hook {
+            hook(&self.rpl_flags);
+        }
+        return Ok(bulk_transfer_cycles(vx));
+    }
+
+    // Fx85
+    fn op_ldxr(&mut self, vx: u8) -> Result<usize, String> {
+        self.check_rpl_range(vx)?;
+        let count = vx as usize + 1;
+        self.registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+        return Ok(bulk_transfer_cycles(vx));
+    }
+
+    // SUPER-CHIP only has flags for V0-V7
+    fn check_rpl_range(&self, vx: u8) -> Result<(), String> {
+        let flags = if self.xochip { RPL_FLAGS } else { 8 };
+        if vx as usize >= flags {
+            return Err(format!("rpl flags only hold V0-V{:X}", flags - 1));
+        }
+        return Ok(());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+// Static description of an opcode handled by step, an opcode op is this one
+// when op & mask == value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub pattern: &'static str,
+    pub mask: u16,
+    pub value: u16,
+    pub mnemonic: &'static str,
+    pub description: &'static str,
+}
+
+impl OpcodeInfo {
+    pub fn matches(&self, op: u16) -> bool {
+        return op & self.mask == self.value;
+    }
+}
+
+const fn info(
+    pattern: &'static str,
+    mask: u16,
+    value: u16,
+    mnemonic: &'static str,
+    description: &'static str,
+) -> OpcodeInfo {
+    return OpcodeInfo { pattern, mask, value, mnemonic, description };
+}
+
+// Masks follow decode, which ignores the second nibble of 00Cn/00Ex/00Fx and the last
+// nibble of 5xy0/9xy0
+const OPCODES: [OpcodeInfo; 47] = [
+    info("00E0", 0xF0FF, 0x00E0, "CLS", "Clear the display"),
+    info("00EE", 0xF0FF, 0x00EE, "RET", "Return from a subroutine"),
+    info("00Cn", 0xF0F0, 0x00C0, "SCD nibble", "Scroll the display down n rows"),
+    info("00FB", 0xF0FF, 0x00FB, "SCR", "Scroll the display right 4 pixels"),
+    info("00FC", 0xF0FF, 0x00FC, "SCL", "Scroll the display left 4 pixels"),
+    info("00FD", 0xF0FF, 0x00FD, "EXIT", "Halt the machine"),
+    info("00FE", 0xF0FF, 0x00FE, "LOW", "Switch to the 64x32 display and clear it"),
+    info("00FF", 0xF0FF, 0x00FF, "HIGH", "Switch to the 128x64 display and clear it"),
+    info("1nnn", 0xF000, 0x1000, "JP addr", "Jump to nnn"),
+    info("2nnn", 0xF000, 0x2000, "CALL addr", "Call the subroutine at nnn"),
+    info("3xkk", 0xF000, 0x3000, "SE Vx, byte", "Skip the next instruction if Vx == kk"),
+    info("4xkk", 0xF000, 0x4000, "SNE Vx, byte", "Skip the next instruction if Vx != kk"),
+    info("5xy0", 0xF000, 0x5000, "SE Vx, Vy", "Skip the next instruction if Vx == Vy"),
+    info("6xkk", 0xF000, 0x6000, "LD Vx, byte", "Set Vx to kk"),
+    info("7xkk", 0xF000, 0x7000, "ADD Vx, byte", "Add kk to Vx, without carry"),
+    info("8xy0", 0xF00F, 0x8000, "LD Vx, Vy", "Set Vx to Vy"),
+    info("8xy1", 0xF00F, 0x8001, "OR Vx, Vy", "Set Vx to Vx | Vy"),
+    info("8xy2", 0xF00F, 0x8002, "AND Vx, Vy", "Set Vx to Vx & Vy"),
+    info("8xy3", 0xF00F, 0x8003, "XOR Vx, Vy", "Set Vx to Vx ^ Vy"),
+    info("8xy4", 0xF00F, 0x8004, "ADD Vx, Vy", "Add Vy to Vx, VF is set to the carry"),
+    info("8xy5", 0xF00F, 0x8005, "SUB Vx, Vy", "Subtract Vy from Vx, VF is set to not borrow"),
+    info("8xy6", 0xF00F, 0x8006, "SHR Vx, Vy", "Shift right by one, VF is set to the bit shifted out"),
+    info("8xy7", 0xF00F, 0x8007, "SUBN Vx, Vy", "Set Vx to Vy - Vx, VF is set to not borrow"),
+    info("8xyE", 0xF00F, 0x800E, "SHL Vx, Vy", "Shift left by one, VF is set to the bit shifted out"),
+    info("9xy0", 0xF000, 0x9000, "SNE Vx, Vy", "Skip the next instruction if Vx != Vy"),
+    info("Annn", 0xF000, 0xA000, "LD I, addr", "Set I to nnn"),
+    info("Bnnn", 0xF000, 0xB000, "JP V0, addr", "Jump to nnn + V0"),
+    info("Cxkk", 0xF000, 0xC000, "RND Vx, byte", "Set Vx to a random byte & kk"),
+    info("Dxyn", 0xF000, 0xD000, "DRW Vx, Vy, nibble", "Draw n bytes from I at (Vx, Vy), VF is set on collision"),
+    info("Ex9E", 0xF0FF, 0xE09E, "SKP Vx", "Skip the next instruction if key Vx is pressed"),
+    info("ExA1", 0xF0FF, 0xE0A1, "SKNP Vx", "Skip the next instruction if key Vx is not pressed"),
+    info("F000", 0xFFFF, 0xF000, "LD I, long", "Set I to the 16-bit address in the next two bytes (XO-CHIP)"),
+    info("Fn01", 0xF0FF, 0xF001, "PLANE n", "Select the display planes Dxyn and 00E0 work on (XO-CHIP)"),
+    info("F002", 0xFFFF, 0xF002, "AUDIO", "Load the 16 byte audio pattern from I (XO-CHIP)"),
+    info("Fx07", 0xF0FF, 0xF007, "LD Vx, DT", "Set Vx to the delay timer"),
+    info("Fx0A", 0xF0FF, 0xF00A, "LD Vx, K", "Wait for a key press and store it in Vx"),
+    info("Fx15", 0xF0FF, 0xF015, "LD DT, Vx", "Set the delay timer to Vx"),
+    info("Fx18", 0xF0FF, 0xF018, "LD ST, Vx", "Set the sound timer to Vx"),
+    info("Fx1E", 0xF0FF, 0xF01E, "ADD I, Vx", "Add Vx to I"),
+    info("Fx29", 0xF0FF, 0xF029, "LD F, Vx", "Point I to the font sprite for digit Vx"),
+    info("Fx30", 0xF0FF, 0xF030, "LD HF, Vx", "Point I to the big font sprite for digit Vx"),
+    info("Fx33", 0xF0FF, 0xF033, "LD B, Vx", "Store the decimal digits of Vx at I, I+1 and I+2"),
+    info("Fx3A", 0xF0FF, 0xF03A, "PITCH Vx", "Set the XO-CHIP audio pitch to Vx"),
+    info("Fx55", 0xF0FF, 0xF055, "LD [I], Vx", "Store V0 to Vx at I"),
+    info("Fx65", 0xF0FF, 0xF065, "LD Vx, [I]", "Load V0 to Vx from I"),
+    info("Fx75", 0xF0FF, 0xF075, "LD R, Vx", "Save V0 to Vx in the RPL flags"),
+    info("Fx85", 0xF0FF, 0xF085, "LD Vx, R", "Load V0 to Vx from the RPL flags"),
+];
+
+pub fn supported_opcodes() -> &'static [OpcodeInfo] {
+    return &OPCODES;
+}
+
+// Table entry for the given opcode, None if step does not handle it
+pub fn opcode_info(op: u16) -> Option<&'static OpcodeInfo> {
+    return OPCODES.iter().find(|info| info.matches(op));
+}
@@ -0,0 +1,111 @@
+// Behaviors that differ between interpreters. The default matches the
+// behavior of this emulator before quirks were configurable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy6/8xyE shift vy into vx instead of shifting vx in place
+    pub shift_vy: bool,
+    // Sprites wrap around the left and right display edges instead of being
+    // clipped. The starting position always wraps
+    pub wrap_x: bool,
+    // Sprites wrap around the top and bottom display edges instead of being
+    // clipped
+    pub wrap_y: bool,
+    // 8xy1/8xy2/8xy3 reset vf to 0
+    pub vf_reset: bool,
+    // Fx55/Fx65 leave i pointing past the last register accessed
+    pub increment_i: bool,
+    // Bnnn jumps to nnn + vx (x being the high nibble of nnn) instead of v0
+    pub jump_vx: bool,
+    // Fx55/Fx65/Dxyn wrap around to 0x000 instead of erroring past the end of memory
+    pub wrap_i: bool,
+    // Fx1E sets vf to 1 when i goes past 0xFFF and to 0 otherwise (Amiga)
+    pub fx1e_sets_vf: bool,
+    // Dxyn waits for the display, only the first one in a frame draws and
+    // the next one waits for the following frame (COSMAC VIP vblank)
+    pub display_wait: bool,
+    // 00E0 waits for the display like Dxyn under display_wait, so a clear
+    // and a draw take two frames
+    pub cls_waits: bool,
+    // 00E0 sets vf to 0, it is left alone otherwise
+    pub cls_resets_vf: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        return Self {
+            shift_vy: false,
+            wrap_x: true,
+            wrap_y: true,
+            vf_reset: false,
+            increment_i: false,
+            jump_vx: false,
+            wrap_i: false,
+            fx1e_sets_vf: false,
+            display_wait: false,
+            cls_waits: false,
+            cls_resets_vf: false,
+        };
+    }
+}
+
+impl Quirks {
+    // Original COSMAC VIP interpreter
+    pub fn chip8() -> Self {
+        return Self {
+            shift_vy: true,
+            wrap_x: false,
+            wrap_y: false,
+            vf_reset: true,
+            increment_i: true,
+            jump_vx: false,
+            wrap_i: false,
+            fx1e_sets_vf: false,
+            display_wait: true,
+            cls_waits: false,
+            cls_resets_vf: false,
+        };
+    }
+
+    // SUPER-CHIP 1.1
+    pub fn schip() -> Self {
+        return Self {
+            shift_vy: false,
+            wrap_x: false,
+            wrap_y: false,
+            vf_reset: false,
+            increment_i: false,
+            jump_vx: true,
+            wrap_i: false,
+            fx1e_sets_vf: false,
+            display_wait: false,
+            cls_waits: false,
+            cls_resets_vf: false,
+        };
+    }
+
+    // Octo's XO-CHIP
+    pub fn xochip() -> Self {
+        return Self {
+            shift_vy: true,
+            wrap_x: true,
+            wrap_y: true,
+            vf_reset: false,
+            increment_i: true,
+            jump_vx: false,
+            wrap_i: false,
+            fx1e_sets_vf: false,
+            display_wait: false,
+            cls_waits: false,
+            cls_resets_vf: false,
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "chip8" => Some(Self::chip8()),
+            "schip" => Some(Self::schip()),
+            "xochip" => Some(Self::xochip()),
+            _ => None,
+        };
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{
+    Chip8, Quirks, AUDIO_PATTERN_SIZE, DISPLAY_SIZE, MEMORY_SIZE, REGISTERS, RESERVED_MEMORY_SIZE,
+    RPL_FLAGS, XO_MEMORY_SIZE,
+};
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 8;
+
+// Binary save states: the magic and version, then whether XO-CHIP is on and
+// the memory_size bytes of memory it gives, the size of the loaded rom for
+// free_memory, pc, i, the stack (length first),
+// timers, registers, the whole display buffer and whether it is in hires,
+// plane2 and the selected planes, keypad, quirks as bits, halted, pitch,
+// the audio pattern, the RPL flags and the frame count. Multi-byte values
+// are big endian. Configuration other than quirks and the rng are not saved
+impl Chip8 {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MEMORY_SIZE + DISPLAY_SIZE + 128);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.xochip as u8);
+        bytes.extend_from_slice(&self.memory[..self.memory_size()]);
+        bytes.extend_from_slice(&(self.rom_size as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.pc.to_be_bytes());
+        bytes.extend_from_slice(&self.i.to_be_bytes());
+        bytes.extend_from_slice(&(self.stack.len() as u16).to_be_bytes());
+        for addr in &self.stack {
+            bytes.extend_from_slice(&addr.to_be_bytes());
+        }
+        bytes.push(self.delay_timer);
+        bytes.push(self.sound_timer);
+        bytes.extend_from_slice(&self.registers);
+        bytes.extend_from_slice(&self.display);
+        bytes.push(self.hires as u8);
+        bytes.extend_from_slice(&self.plane2);
+        bytes.push(self.planes);
+        bytes.extend_from_slice(&self.keypad.to_be_bytes());
+        bytes.extend_from_slice(&quirk_bits(&self.quirks).to_be_bytes());
+        bytes.push(self.halted as u8);
+        match self.pitch {
+            Some(pitch) => bytes.extend_from_slice(&[1, pitch]),
+            None => bytes.extend_from_slice(&[0, 0]),
+        }
+        match &self.audio_pattern {
+            Some(pattern) => {
+                bytes.push(1);
+                bytes.extend_from_slice(pattern);
+            }
+            None => bytes.extend_from_slice(&[0; AUDIO_PATTERN_SIZE + 1]),
+        }
+        bytes.extend_from_slice(&self.rpl_flags);
+        bytes.extend_from_slice(&self.frame_count.to_be_bytes());
+        return bytes;
+    }
+
+    // Restores a save_state blob, leaving the machine untouched on error
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not a save state".to_string());
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(format!("unsupported save state version {}", version));
+        }
+        let xochip = reader.u8()? != 0;
+        let memory = reader.take(if xochip { XO_MEMORY_SIZE } else { MEMORY_SIZE })?;
+        let rom_size = reader.u16()? as usize;
+        if rom_size > memory.len() - RESERVED_MEMORY_SIZE {
+            return Err(format!("save state rom size {} does not fit in memory", rom_size));
+        }
+        let pc = reader.u16()?;
+        let i = reader.u16()?;
+        let depth = reader.u16()? as usize;
+        if self.max_stack_depth.is_some_and(|max| depth > max) {
+            return Err(format!("save state stack depth {} over the limit", depth));
+        }
+        let mut stack = Vec::with_capacity(depth);
+        for _ in 0..depth {
+            stack.push(reader.u16()?);
+        }
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let registers = reader.take(REGISTERS)?;
+        let display = reader.take(DISPLAY_SIZE)?;
+        let hires = reader.u8()? != 0;
+        let plane2 = reader.take(DISPLAY_SIZE)?;
+        let planes = reader.u8()?;
+        let keypad = reader.u16()?;
+        let quirks = quirks_from_bits(reader.u16()?);
+        let halted = reader.u8()? != 0;
+        let has_pitch = reader.u8()? != 0;
+        let pitch = reader.u8()?;
+        let has_pattern = reader.u8()? != 0;
+        let pattern: [u8; AUDIO_PATTERN_SIZE] = reader.take(AUDIO_PATTERN_SIZE)?.try_into().unwrap();
+        let rpl_flags = reader.take(RPL_FLAGS)?;
+        let frame_count = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        if reader.pos != bytes.len() {
+            return Err("trailing bytes after save state".to_string());
+        }
+
+        self.xochip = xochip;
+        self.memory[..memory.len()].copy_from_slice(memory);
+        self.memory[memory.len()..].fill(0);
+        self.rom_size = rom_size;
+        self.pc = pc;
+        self.i = i;
+        self.stack = stack;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.timer_phase = 0.0;
+        self.registers.copy_from_slice(registers);
+        self.display.copy_from_slice(display);
+        self.hires = hires;
+        self.plane2.copy_from_slice(plane2);
+        self.planes = planes & 0b11;
+        self.keypad = keypad;
+        self.quirks = quirks;
+        self.halted = halted;
+        self.pitch = if has_pitch { Some(pitch) } else { None };
+        self.audio_pattern = if has_pattern { Some(pattern) } else { None };
+        self.rpl_flags.copy_from_slice(rpl_flags);
+        self.frame_count = frame_count;
+        self.waiting_for_key = false;
+        self.history.clear();
+        return Ok(());
+    }
+
+    // A fresh machine with default configuration and a new rng, resumed
+    // from a save_state blob
+    pub fn from_state(bytes: &[u8]) -> Result<Chip8, String> {
+        let mut chip8 = Chip8::new();
+        chip8.load_state(bytes)?;
+        return Ok(chip8);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos + len;
+        if end > self.bytes.len() {
+            return Err("truncated save state".to_string());
+        }
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        return Ok(slice);
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        return Ok(u16::from_be_bytes([bytes[0], bytes[1]]));
+    }
+}
+
+fn quirk_bits(quirks: &Quirks) -> u16 {
+    return [
+        quirks.shift_vy,
+        quirks.wrap_x,
+        quirks.vf_reset,
+        quirks.increment_i,
+        quirks.jump_vx,
+        quirks.wrap_i,
+        quirks.fx1e_sets_vf,
+        quirks.wrap_y,
+        quirks.display_wait,
+        quirks.cls_waits,
+        quirks.cls_resets_vf,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (idx, &set)| bits | (set as u16) << idx);
+}
+
+fn quirks_from_bits(bits: u16) -> Quirks {
+    let bit = |idx: u16| bits >> idx & 1 != 0;
+    return Quirks {
+        shift_vy: bit(0),
+        wrap_x: bit(1),
+        wrap_y: bit(7),
+        display_wait: bit(8),
+        cls_waits: bit(9),
+        cls_resets_vf: bit(10),
+        vf_reset: bit(2),
+        increment_i: bit(3),
+        jump_vx: bit(4),
+        wrap_i: bit(5),
+        fx1e_sets_vf: bit(6),
+    };
+}
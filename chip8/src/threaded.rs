@@ -0,0 +1,150 @@
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{Chip8, Chip8Config, DISPLAY_SIZE};
+
+const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// Sent to the emulation thread, handled at the start of the next frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    LoadRom(Vec<u8>),
+    SetKeypad(u16),
+    Pause(bool),
+    // Power-on state with the last loaded rom loaded again
+    Reset,
+    // Answered with Output::State
+    SaveState,
+}
+
+// Published by the emulation thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    // After every frame that ran, rows of the display and of the XO-CHIP
+    // plane2 are packed at the width hires gives
+    Frame {
+        display: Box<[u8; DISPLAY_SIZE]>,
+        plane2: Box<[u8; DISPLAY_SIZE]>,
+        hires: bool,
+        beeping: bool,
+    },
+    State(Vec<u8>),
+    // A command or frame failed, the machine is paused until resumed
+    Error(String),
+}
+
+// A machine running at 60 frames a second on its own thread. Hooks are not
+// Send, the machine is built on the thread from config so it never has to
+// be. Dropping it stops the thread
+pub struct Chip8Thread {
+    commands: Option<Sender<Command>>,
+    outputs: Receiver<Output>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Chip8Thread {
+    // Starts paused until a rom is loaded
+    pub fn spawn(config: Chip8Config) -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let (output_tx, outputs) = mpsc::channel();
+        let handle = thread::spawn(move || run(config, command_rx, output_tx));
+        return Self { commands: Some(commands), outputs, handle: Some(handle) };
+    }
+
+    // Fails once the thread is gone
+    pub fn send(&self, command: Command) -> Result<(), String> {
+        let commands = self.commands.as_ref().unwrap();
+        return commands.send(command).map_err(|_| "emulation thread stopped".to_string());
+    }
+
+    // Outputs in the order they were published, reading them is up to the
+    // caller, e.g. try_recv once per rendered frame
+    pub fn outputs(&self) -> &Receiver<Output> {
+        return &self.outputs;
+    }
+}
+
+impl Drop for Chip8Thread {
+    fn drop(&mut self) {
+        // Hanging up is what stops the thread
+        self.commands = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(config: Chip8Config, commands: Receiver<Command>, outputs: Sender<Output>) {
+    let mut chip8 = Chip8::new_with_config(config);
+    let mut rom: Option<Vec<u8>> = None;
+    let mut paused = false;
+    let mut next_frame = Instant::now();
+    loop {
+        loop {
+            let command = match commands.try_recv() {
+                Ok(command) => command,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            };
+            let result = match command {
+                Command::LoadRom(bytes) => {
+                    chip8.reset();
+                    let result = chip8.load_rom(&bytes);
+                    rom = result.is_ok().then_some(bytes);
+                    result
+                }
+                Command::SetKeypad(keypad) => {
+                    chip8.set_keypad(keypad);
+                    Ok(())
+                }
+                Command::Pause(pause) => {
+                    paused = pause;
+                    Ok(())
+                }
+                Command::Reset => {
+                    chip8.reset();
+                    match &rom {
+                        Some(bytes) => chip8.load_rom(bytes),
+                        None => Ok(()),
+                    }
+                }
+                Command::SaveState => {
+                    let _ = outputs.send(Output::State(chip8.save_state()));
+                    Ok(())
+                }
+            };
+            if let Err(err) = result {
+                paused = true;
+                let _ = outputs.send(Output::Error(err));
+            }
+        }
+
+        if !paused && rom.is_some() {
+            let output = match chip8.frame() {
+                Ok(_) => Output::Frame {
+                    display: Box::new(chip8.display),
+                    plane2: Box::new(chip8.plane2),
+                    hires: chip8.is_hires(),
+                    beeping: chip8.is_beeping(),
+                },
+                Err(err) => {
+                    paused = true;
+                    Output::Error(err)
+                }
+            };
+            if outputs.send(output).is_err() {
+                return;
+            }
+        }
+
+        next_frame += FRAME_INTERVAL;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            // Fell behind, do not try to catch up with a burst of frames
+            next_frame = now;
+        }
+    }
+}
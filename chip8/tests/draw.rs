@@ -0,0 +1,345 @@
+use chip8::{Chip8, Quirks, DISPLAY_WIDTH};
+
+const COLUMNS: usize = DISPLAY_WIDTH / 8;
+
+// Draws the 8x1 row 0xFF at (x, 0)
+fn draw_row(chip8: &mut Chip8, x: u8) {
+    chip8.memory[0x300] = 0xFF;
+    chip8.i = 0x300;
+    chip8.registers[0] = x;
+    chip8.registers[1] = 0;
+    chip8.step(0xD0, 0x11).unwrap();
+}
+
+#[test]
+fn aligned_sprite_in_last_column_does_not_wrap() {
+    let mut chip8 = Chip8::new();
+    draw_row(&mut chip8, (DISPLAY_WIDTH - 8) as u8);
+    assert_eq!(chip8.display[COLUMNS - 1], 0xFF);
+    assert_eq!(chip8.display[0], 0x00);
+}
+
+#[test]
+fn unaligned_sprite_in_last_column_wraps_to_same_row() {
+    let mut chip8 = Chip8::new();
+    draw_row(&mut chip8, (DISPLAY_WIDTH - 4) as u8);
+    assert_eq!(chip8.display[COLUMNS - 1], 0x0F);
+    assert_eq!(chip8.display[0], 0xF0);
+    assert_eq!(chip8.display[COLUMNS], 0x00);
+}
+
+#[test]
+fn unaligned_sprite_in_last_column_is_clipped_with_quirk() {
+    let mut chip8 = Chip8::with_quirks(Quirks { wrap_x: false, ..Quirks::default() });
+    draw_row(&mut chip8, (DISPLAY_WIDTH - 4) as u8);
+    assert_eq!(chip8.display[COLUMNS - 1], 0x0F);
+    assert_eq!(chip8.display[0], 0x00);
+}
+
+#[test]
+fn display_renders_framebuffer_as_text() {
+    let mut chip8 = Chip8::new();
+    chip8.display[0] = 0b1010_0000;
+    chip8.display[DISPLAY_WIDTH * chip8::DISPLAY_HEIGHT / 8 - 1] = 0b0000_0001;
+    let text = chip8.to_string();
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows.len(), chip8::DISPLAY_HEIGHT);
+    assert!(rows.iter().all(|row| row.len() == DISPLAY_WIDTH));
+    assert_eq!(rows[0].trim_end(), "# #");
+    assert!(rows[chip8::DISPLAY_HEIGHT - 1].ends_with(" #"));
+}
+
+#[test]
+fn window_coordinates_map_to_pixels() {
+    let size = (DISPLAY_WIDTH, chip8::DISPLAY_HEIGHT);
+    assert_eq!(chip8::window_to_pixel(0, 0, 0, 0, 16, size), Some((0, 0)));
+    assert_eq!(chip8::window_to_pixel(1023, 511, 0, 0, 16, size), Some((63, 31)));
+    assert_eq!(chip8::window_to_pixel(1024, 0, 0, 0, 16, size), None);
+    assert_eq!(chip8::window_to_pixel(5, 5, 10, 10, 16, size), None);
+    assert_eq!(chip8::window_to_pixel(42, 26, 10, 10, 16, size), Some((2, 1)));
+    let hires = (chip8::HIRES_WIDTH, chip8::HIRES_HEIGHT);
+    assert_eq!(chip8::window_to_pixel(1023, 511, 0, 0, 8, hires), Some((127, 63)));
+
+    let mut chip8 = Chip8::new();
+    chip8.display[chip8::pixel_byte_index(9, 1, DISPLAY_WIDTH)] = 0b0100_0000;
+    assert!(chip8.pixel(9, 1));
+    assert!(!chip8.pixel(8, 1));
+}
+
+#[test]
+fn collisions_are_counted() {
+    let mut chip8 = Chip8::new();
+    draw_row(&mut chip8, 0);
+    assert_eq!(chip8.collision_count(), 0);
+    draw_row(&mut chip8, 4);
+    draw_row(&mut chip8, 4);
+    assert_eq!(chip8.collision_count(), 2);
+    chip8.reset_collision_count();
+    assert_eq!(chip8.collision_count(), 0);
+}
+
+#[test]
+fn display_exports_as_plain_pbm() {
+    let mut chip8 = Chip8::new();
+    chip8.display[0] = 0b1010_0000;
+    let pbm = chip8.display_pbm();
+    let lines: Vec<&str> = pbm.lines().collect();
+    assert_eq!(lines[0], "P1");
+    assert_eq!(lines[1], format!("{} {}", DISPLAY_WIDTH, chip8::DISPLAY_HEIGHT));
+    assert_eq!(lines.len(), chip8::DISPLAY_HEIGHT + 2);
+    assert!(lines[2].starts_with("10100000"));
+    assert!(lines[3..].iter().all(|row| *row == "0".repeat(DISPLAY_WIDTH)));
+}
+
+#[test]
+fn zero_height_sprite_draws_nothing_in_chip8_mode() {
+    for quirks in [Quirks::default(), Quirks::chip8(), Quirks::schip(), Quirks::xochip()] {
+        // Both draws land in one frame, keep the second one from waiting
+        let mut chip8 = Chip8::with_quirks(Quirks { display_wait: false, ..quirks });
+        draw_row(&mut chip8, 0);
+        chip8.registers[0xf] = 1;
+        chip8.memory[0x300..0x320].fill(0xFF);
+        chip8.step(0xD0, 0x10).unwrap();
+        assert_eq!(chip8.registers[0xf], 0);
+        assert_eq!(chip8.display[0], 0xFF);
+        assert!(chip8.display[1..].iter().all(|&byte| byte == 0));
+    }
+}
+
+#[test]
+fn collision_log_lists_pixels_turned_off() {
+    let mut chip8 = Chip8::new();
+    draw_row(&mut chip8, 60);
+    assert!(chip8.take_collision_pixels().is_empty());
+    chip8.enable_collision_log(true);
+    // Both rows wrap around, they overlap at x 62 and 63 then 0 to 3
+    draw_row(&mut chip8, 62);
+    assert_eq!(chip8.take_collision_pixels(), [(62, 0), (63, 0), (0, 0), (1, 0), (2, 0), (3, 0)]);
+    assert!(chip8.take_collision_pixels().is_empty());
+}
+
+#[test]
+fn sprite_across_bottom_right_corner_wraps_per_axis() {
+    let left: Vec<usize> = (0..4).collect();
+    let right: Vec<usize> = (DISPLAY_WIDTH - 4..DISPLAY_WIDTH).collect();
+    let bottom = chip8::DISPLAY_HEIGHT - 1;
+    for (wrap_x, wrap_y) in [(true, true), (true, false), (false, true), (false, false)] {
+        let mut chip8 = Chip8::with_quirks(Quirks { wrap_x, wrap_y, ..Quirks::default() });
+        // An 8x2 sprite whose top left pixel is 4 from the right, on the last row
+        chip8.memory[0x300..0x302].fill(0xFF);
+        chip8.i = 0x300;
+        chip8.registers[0] = (DISPLAY_WIDTH - 4) as u8;
+        chip8.registers[1] = bottom as u8;
+        chip8.step(0xD0, 0x12).unwrap();
+
+        let mut expected = vec![];
+        for y in [Some(bottom), wrap_y.then_some(0)].into_iter().flatten() {
+            let columns = if wrap_x { [&right[..], &left[..]].concat() } else { right.clone() };
+            expected.extend(columns.into_iter().map(|x| (x, y)));
+        }
+        let mut lit = vec![];
+        for y in 0..chip8::DISPLAY_HEIGHT {
+            for x in 0..DISPLAY_WIDTH {
+                if chip8.pixel(x, y) {
+                    lit.push((x, y));
+                }
+            }
+        }
+        expected.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(lit, expected, "wrap_x {} wrap_y {}", wrap_x, wrap_y);
+    }
+}
+
+#[test]
+fn clear_display_only_touches_the_display() {
+    let mut chip8 = Chip8::new();
+    draw_row(&mut chip8, 0);
+    let pc = chip8.pc;
+    chip8.registers[0xf] = 1;
+    chip8.clear_display();
+    assert!(chip8.display_buffer().iter().all(|&byte| byte == 0));
+    assert_eq!(chip8.registers[0xf], 1);
+    assert_eq!(chip8.pc, pc);
+}
+
+#[test]
+fn wide_sprites_draw_two_bytes_a_row() {
+    use chip8::Chip8Config;
+
+    for x in [0usize, 3, 8, 13, DISPLAY_WIDTH - 5] {
+        for wrap_x in [true, false] {
+            let mut chip8 = Chip8::new_with_config(Chip8Config {
+                quirks: Quirks { wrap_x, ..Quirks::default() },
+                wide_sprites: true,
+                ..Chip8Config::default()
+            });
+            // One 16 pixel row with a gap in the middle: ######## ##..####
+            chip8.memory[0x300..0x302].copy_from_slice(&[0xFF, 0xCF]);
+            chip8.i = 0x300;
+            chip8.registers[0] = x as u8;
+            chip8.registers[1] = 0;
+            chip8.step(0xD0, 0x11).unwrap();
+            assert_eq!(chip8.registers[0xf], 0);
+
+            let mut expected = vec![];
+            for dx in (0..16).filter(|&dx| dx != 10 && dx != 11) {
+                if wrap_x || x + dx < DISPLAY_WIDTH {
+                    expected.push((x + dx) % DISPLAY_WIDTH);
+                }
+            }
+            expected.sort();
+            let lit: Vec<usize> = (0..DISPLAY_WIDTH).filter(|&px| chip8.pixel(px, 0)).collect();
+            assert_eq!(lit, expected, "x {} wrap_x {}", x, wrap_x);
+
+            // Erasing the sprite's last pixel collides through the second byte
+            chip8.memory[0x300..0x302].copy_from_slice(&[0x00, 0x01]);
+            chip8.step(0xD0, 0x11).unwrap();
+            let last_visible = wrap_x || x + 15 < DISPLAY_WIDTH;
+            assert_eq!(chip8.registers[0xf], last_visible as u8, "x {} wrap_x {}", x, wrap_x);
+        }
+    }
+}
+
+#[test]
+fn hires_switches_to_128x64_and_draws_16x16_sprites() {
+    use chip8::{HIRES_HEIGHT, HIRES_WIDTH};
+
+    let mut chip8 = Chip8::new();
+    chip8.display[0] = 0xFF;
+    chip8.step(0x00, 0xFF).unwrap();
+    assert!(chip8.is_hires());
+    assert_eq!((chip8.display_width(), chip8.display_height()), (HIRES_WIDTH, HIRES_HEIGHT));
+    assert!(chip8.display_buffer().iter().all(|&byte| byte == 0));
+
+    // A solid 16x16 sprite in the bottom right corner, past 64x32
+    chip8.memory[0x300..0x320].fill(0xFF);
+    chip8.i = 0x300;
+    chip8.registers[0] = (HIRES_WIDTH - 16) as u8;
+    chip8.registers[1] = (HIRES_HEIGHT - 16) as u8;
+    chip8.step(0xD0, 0x10).unwrap();
+    assert_eq!(chip8.registers[0xf], 0);
+    let lit = (0..HIRES_HEIGHT)
+        .flat_map(|y| (0..HIRES_WIDTH).map(move |x| (x, y)))
+        .filter(|&(x, y)| chip8.pixel(x, y))
+        .count();
+    assert_eq!(lit, 16 * 16);
+    assert!(chip8.pixel(HIRES_WIDTH - 1, HIRES_HEIGHT - 1));
+    assert!(!chip8.pixel(HIRES_WIDTH - 17, HIRES_HEIGHT - 1));
+    let pbm = chip8.display_pbm();
+    assert!(pbm.starts_with("P1\n128 64\n"));
+    assert!(pbm.lines().all(|line| line.len() <= 70));
+    let bits: String = pbm.lines().skip(2).collect();
+    assert_eq!(bits.len(), HIRES_WIDTH * HIRES_HEIGHT);
+    assert!(bits.ends_with(&"1".repeat(16)));
+    assert_eq!(chip8.to_string().lines().count(), HIRES_HEIGHT);
+
+    chip8.step(0xD0, 0x10).unwrap();
+    assert_eq!(chip8.registers[0xf], 1);
+    chip8.step(0xD0, 0x10).unwrap();
+    chip8.step(0x00, 0xFE).unwrap();
+    assert!(!chip8.is_hires());
+    assert_eq!(chip8.display_buffer().len(), DISPLAY_WIDTH * chip8::DISPLAY_HEIGHT / 8);
+    assert!(chip8.display.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn xochip_planes_draw_and_clear_separately() {
+    let mut chip8 = Chip8::new();
+    assert!(chip8.step(0xF3, 0x01).is_err());
+
+    chip8.set_xochip(true);
+    // Both planes take their own 2 row sprite, one after the other
+    chip8.memory[0x300..0x304].copy_from_slice(&[0x80, 0x00, 0xC0, 0x00]);
+    chip8.i = 0x300;
+    chip8.registers[0] = 0;
+    chip8.registers[1] = 0;
+    chip8.step(0xF3, 0x01).unwrap();
+    chip8.step(0xD0, 0x12).unwrap();
+    assert_eq!(chip8.pixel_color(0, 0), 3);
+    assert_eq!(chip8.pixel_color(1, 0), 2);
+    assert_eq!(chip8.pixel_color(0, 1), 0);
+
+    chip8.step(0xF2, 0x01).unwrap();
+    chip8.step(0x00, 0xE0).unwrap();
+    assert_eq!(chip8.pixel_color(0, 0), 1);
+    assert!(chip8.plane2_buffer().iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn scrolls_shift_across_byte_boundaries() {
+    use chip8::{HIRES_HEIGHT, HIRES_WIDTH};
+
+    let mut chip8 = Chip8::new();
+    draw_row(&mut chip8, 4);
+    assert_eq!(chip8.display[..2], [0x0F, 0xF0]);
+    chip8.step(0x00, 0xFB).unwrap();
+    assert_eq!(chip8.display[..2], [0x00, 0xFF]);
+    chip8.step(0x00, 0xFC).unwrap();
+    chip8.step(0x00, 0xFC).unwrap();
+    assert_eq!(chip8.display[..2], [0xFF, 0x00]);
+    // Pixels pushed off the edge are gone rather than wrapped
+    chip8.step(0x00, 0xFC).unwrap();
+    chip8.step(0x00, 0xFC).unwrap();
+    assert!(chip8.display.iter().all(|&byte| byte == 0));
+
+    draw_row(&mut chip8, (DISPLAY_WIDTH - 8) as u8);
+    chip8.step(0x00, 0xC3).unwrap();
+    let lit: Vec<(usize, usize)> = (0..chip8::DISPLAY_HEIGHT)
+        .flat_map(|y| (0..DISPLAY_WIDTH).map(move |x| (x, y)))
+        .filter(|&(x, y)| chip8.pixel(x, y))
+        .collect();
+    assert_eq!(lit, (DISPLAY_WIDTH - 8..DISPLAY_WIDTH).map(|x| (x, 3)).collect::<Vec<_>>());
+    chip8.step(0x00, 0xCF).unwrap();
+    chip8.step(0x00, 0xCF).unwrap();
+    assert!(chip8.display.iter().all(|&byte| byte == 0));
+
+    // Hires rows are twice as wide, the last one scrolls off the bottom
+    chip8.step(0x00, 0xFF).unwrap();
+    chip8.memory[0x300] = 0x80;
+    chip8.registers[0] = (HIRES_WIDTH - 1) as u8;
+    chip8.registers[1] = (HIRES_HEIGHT - 2) as u8;
+    chip8.step(0xD0, 0x11).unwrap();
+    chip8.step(0x00, 0xFC).unwrap();
+    assert!(chip8.pixel(HIRES_WIDTH - 5, HIRES_HEIGHT - 2));
+    chip8.step(0x00, 0xC1).unwrap();
+    assert!(chip8.pixel(HIRES_WIDTH - 5, HIRES_HEIGHT - 1));
+    chip8.step(0x00, 0xC1).unwrap();
+    assert!(chip8.display_buffer().iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn scrolls_only_move_the_selected_planes() {
+    let mut chip8 = Chip8::new();
+    chip8.set_xochip(true);
+    chip8.memory[0x300..0x302].copy_from_slice(&[0xF0, 0xF0]);
+    chip8.i = 0x300;
+    chip8.registers[0] = 0;
+    chip8.registers[1] = 0;
+    chip8.step(0xF3, 0x01).unwrap();
+    chip8.step(0xD0, 0x11).unwrap();
+    chip8.step(0xF2, 0x01).unwrap();
+    chip8.step(0x00, 0xC1).unwrap();
+    chip8.step(0x00, 0xFB).unwrap();
+    assert_eq!(chip8.pixel_color(0, 0), 1);
+    assert_eq!(chip8.pixel_color(4, 1), 2);
+    assert_eq!(chip8.plane2_buffer()[COLUMNS], 0x0F);
+}
+
+#[test]
+fn xochip_draws_16x16_sprites_in_lores() {
+    let mut chip8 = Chip8::new();
+    chip8.set_xochip(true);
+    chip8.memory[0x300..0x320].fill(0xFF);
+    chip8.i = 0x300;
+    chip8.registers[0] = 0;
+    chip8.registers[1] = 0;
+    chip8.step(0xD0, 0x10).unwrap();
+    assert!(!chip8.is_hires());
+    let lit = (0..chip8::DISPLAY_HEIGHT)
+        .flat_map(|y| (0..DISPLAY_WIDTH).map(move |x| (x, y)))
+        .filter(|&(x, y)| chip8.pixel(x, y))
+        .count();
+    assert_eq!(lit, 16 * 16);
+    assert!(chip8.pixel(15, 15));
+    assert!(!chip8.pixel(16, 0));
+}
@@ -0,0 +1,43 @@
+use chip8::{Chip8, Quirks};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Same idea as the cargo fuzz target, run on a fixed seed so regressions
+// show up in the regular test suite
+#[test]
+fn random_opcodes_never_panic() {
+    let mut rng = StdRng::seed_from_u64(0xF022);
+    let presets = [
+        Quirks::default(),
+        Quirks::chip8(),
+        Quirks::schip(),
+        Quirks { wrap_i: true, ..Quirks::xochip() },
+    ];
+    for round in 0..4_000 {
+        let mut chip8 = Chip8::with_quirks(presets[round % presets.len()]);
+        chip8.set_check_cycle_costs(true);
+        chip8.set_xochip(round % 3 == 0);
+        let memory_size = chip8.memory_size();
+        rng.fill(&mut chip8.memory[..memory_size]);
+        rng.fill(&mut chip8.registers[..]);
+        // The last word of memory now and then, where advancing pc overflows
+        chip8.pc = if rng.gen_bool(0.1) { (memory_size - 2) as u16 } else { rng.gen() };
+        chip8.i = rng.gen();
+        chip8.delay_timer = rng.gen();
+        chip8.sound_timer = rng.gen();
+        chip8.keypad = rng.gen();
+        for _ in 0..rng.gen_range(0..20) {
+            chip8.stack.push(rng.gen());
+        }
+        // Half the rounds fetch from memory through tick, which also covers
+        // advancing pc and rewind
+        let fetch = round % 2 == 1;
+        if fetch && rng.gen_bool(0.5) {
+            chip8.enable_rewind(4);
+        }
+        for _ in 0..8 {
+            let result = if fetch { chip8.tick() } else { chip8.execute_raw(rng.gen(), rng.gen()) };
+            assert!(!result.as_ref().is_err_and(|err| err.contains("cycles")), "{:?}", result);
+        }
+    }
+}
@@ -0,0 +1,494 @@
+use chip8::{Chip8, Quirks};
+
+#[test]
+fn store_and_load_all_registers_including_vf() {
+    let mut chip8 = Chip8::new();
+    for idx in 0..16 {
+        chip8.registers[idx] = 0xA0 + idx as u8;
+    }
+    chip8.i = 0x300;
+    chip8.step(0xFF, 0x55).unwrap();
+    assert_eq!(&chip8.memory[0x300..0x310], &chip8.registers[..]);
+
+    chip8.registers = [0; 16];
+    chip8.step(0xFF, 0x65).unwrap();
+    for idx in 0..16 {
+        assert_eq!(chip8.registers[idx], 0xA0 + idx as u8);
+    }
+}
+
+#[test]
+fn store_past_end_of_memory_errors() {
+    let mut chip8 = Chip8::new();
+    chip8.i = 0xFFE;
+    assert!(chip8.step(0xFF, 0x55).is_err());
+    assert!(chip8.step(0xFF, 0x65).is_err());
+    assert_eq!(chip8.memory[0xFFE..chip8::MEMORY_SIZE], [0, 0]);
+}
+
+#[test]
+fn store_past_end_of_memory_wraps_with_quirk() {
+    let mut chip8 = Chip8::with_quirks(Quirks { wrap_i: true, ..Quirks::default() });
+    chip8.registers[0x2] = 0x42;
+    chip8.i = 0xFFE;
+    chip8.step(0xFF, 0x55).unwrap();
+    assert_eq!(chip8.memory[0x000], 0x42);
+}
+
+#[test]
+fn key_skips_only_use_low_nibble_of_vx() {
+    let mut chip8 = Chip8::new();
+    chip8.registers[0] = 0x13;
+    chip8.keypad = 1 << 0x3;
+    chip8.step(0xE0, 0x9E).unwrap();
+    assert_eq!(chip8.pc, 0x202);
+    chip8.step(0xE0, 0xA1).unwrap();
+    assert_eq!(chip8.pc, 0x202);
+}
+
+// The flag is written after the result, so vf ends up holding the flag when
+// it is also an operand
+#[test]
+fn add_into_vf_keeps_carry() {
+    let mut chip8 = Chip8::new();
+    chip8.registers[0xf] = 0xFF;
+    chip8.registers[1] = 0x02;
+    chip8.step(0x8F, 0x14).unwrap();
+    assert_eq!(chip8.registers[0xf], 1);
+
+    chip8.registers[0xf] = 0x01;
+    chip8.step(0x8F, 0x14).unwrap();
+    assert_eq!(chip8.registers[0xf], 0);
+}
+
+#[test]
+fn sub_into_vf_keeps_borrow() {
+    let mut chip8 = Chip8::new();
+    chip8.registers[0xf] = 0x05;
+    chip8.registers[1] = 0x02;
+    chip8.step(0x8F, 0x15).unwrap();
+    assert_eq!(chip8.registers[0xf], 1);
+
+    chip8.registers[0xf] = 0x01;
+    chip8.step(0x8F, 0x15).unwrap();
+    assert_eq!(chip8.registers[0xf], 0);
+}
+
+#[test]
+fn add_vf_operand_uses_value_before_flag() {
+    let mut chip8 = Chip8::new();
+    chip8.registers[1] = 0xF0;
+    chip8.registers[0xf] = 0x20;
+    chip8.step(0x81, 0xF4).unwrap();
+    assert_eq!(chip8.registers[1], 0x10);
+    assert_eq!(chip8.registers[0xf], 1);
+
+    chip8.registers[1] = 0x10;
+    chip8.registers[0xf] = 0x20;
+    chip8.step(0x81, 0xF5).unwrap();
+    assert_eq!(chip8.registers[1], 0xF0);
+    assert_eq!(chip8.registers[0xf], 0);
+}
+
+#[test]
+fn rnd_uses_constant_when_set() {
+    let mut chip8 = Chip8::new();
+    chip8.set_rng_constant(Some(0xFF));
+    chip8.step(0xC3, 0x3C).unwrap();
+    assert_eq!(chip8.registers[3], 0x3C);
+}
+
+#[test]
+fn supported_opcodes_match_decoder() {
+    for info in chip8::supported_opcodes() {
+        let [op0, op1] = info.value.to_be_bytes();
+        assert!(chip8::Instruction::decode(op0, op1).is_some(), "{}", info.pattern);
+    }
+    for op in 0..=0xFFFFu16 {
+        let [op0, op1] = op.to_be_bytes();
+        let decoded = chip8::Instruction::decode(op0, op1).is_some();
+        assert_eq!(chip8::opcode_info(op).is_some(), decoded, "0x{:04X}", op);
+    }
+}
+
+#[test]
+fn exit_halts_and_fires_hook_once() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let halts = Rc::new(Cell::new(0));
+    let mut chip8 = Chip8::new();
+    let counter = halts.clone();
+    chip8.set_halt_hook(Box::new(move || counter.set(counter.get() + 1)));
+    // 0x200: EXIT
+    chip8.load_rom(&[0x00, 0xFD]).unwrap();
+    chip8.run_frames(3).unwrap();
+    assert!(chip8.is_halted());
+    assert_eq!(chip8.pc, 0x202);
+    assert_eq!(halts.get(), 1);
+}
+
+#[test]
+fn jumps_out_of_memory_error_immediately() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.step(0x1F, 0xFF), Err("jp to out-of-bounds address 0xfff".to_string()));
+    assert_eq!(chip8.step(0x2F, 0xFF), Err("call to out-of-bounds address 0xfff".to_string()));
+    assert_eq!(chip8.call_depth(), 0);
+    chip8.registers[0] = 0x10;
+    assert_eq!(chip8.step(0xBF, 0xF0), Err("jp to out-of-bounds address 0x1000".to_string()));
+    assert_eq!(chip8.pc, 0x200);
+}
+
+#[test]
+fn xochip_jumps_reach_past_4k() {
+    let mut chip8 = Chip8::new();
+    chip8.set_xochip(true);
+    chip8.registers[0] = 0x10;
+    chip8.step(0xBF, 0xF0).unwrap();
+    assert_eq!(chip8.pc, 0x1000);
+    chip8.step(0x1F, 0xFF).unwrap();
+    assert_eq!(chip8.pc, 0xFFF);
+}
+
+#[test]
+fn pokes_are_written_every_frame() {
+    let mut chip8 = Chip8::new();
+    // 0x200: LD I, 0x300
+    // 0x202: LD [I], V0
+    // 0x204: JP 0x204
+    chip8.load_rom(&[0xA3, 0x00, 0xF0, 0x55, 0x12, 0x04]).unwrap();
+    chip8.add_poke(0x300, 0x03);
+    chip8.frame().unwrap();
+    // The rom overwrote the poke during the frame
+    assert_eq!(chip8.memory[0x300], 0x00);
+    chip8.frame().unwrap();
+    assert_eq!(chip8.memory[0x300], 0x03);
+    chip8.clear_pokes();
+    chip8.memory[0x300] = 0x07;
+    chip8.frame().unwrap();
+    assert_eq!(chip8.memory[0x300], 0x07);
+}
+
+#[test]
+fn load_memory_image_replaces_everything() {
+    let mut chip8 = Chip8::new();
+    let mut image = [0u8; chip8::MEMORY_SIZE];
+    image[0] = 0xAA;
+    image[0x100] = 0x60;
+    image[0x101] = 0x42;
+    chip8.load_memory_image(&image, 0x100).unwrap();
+    assert_eq!(chip8.memory[0], 0xAA);
+    chip8.tick().unwrap();
+    assert_eq!(chip8.registers[0], 0x42);
+    assert!(chip8.load_memory_image(&image, 0xFFF).is_err());
+}
+
+#[test]
+fn add_i_sets_vf_on_overflow_with_quirk() {
+    let mut chip8 = Chip8::with_quirks(Quirks { fx1e_sets_vf: true, ..Quirks::default() });
+    chip8.i = 0xFFE;
+    chip8.registers[1] = 0x01;
+    chip8.step(0xF1, 0x1E).unwrap();
+    assert_eq!(chip8.i, 0xFFF);
+    assert_eq!(chip8.registers[0xf], 0);
+    chip8.step(0xF1, 0x1E).unwrap();
+    assert_eq!(chip8.i, 0x1000);
+    assert_eq!(chip8.registers[0xf], 1);
+}
+
+#[test]
+fn add_i_leaves_vf_alone_without_quirk() {
+    let mut chip8 = Chip8::new();
+    chip8.i = 0xFFF;
+    chip8.registers[1] = 0x01;
+    chip8.registers[0xf] = 0x55;
+    chip8.step(0xF1, 0x1E).unwrap();
+    assert_eq!(chip8.i, 0x1000);
+    assert_eq!(chip8.registers[0xf], 0x55);
+}
+
+#[test]
+fn custom_handler_runs_for_unknown_opcodes() {
+    let mut chip8 = Chip8::new();
+    // E0xx: load xx into V0, anything else is left to the invalid op policy
+    chip8.set_custom_opcode_handler(Box::new(|context, op0, op1| {
+        if op0 != 0xE0 {
+            return None;
+        }
+        context.registers[0] = op1;
+        Some(10)
+    }));
+    assert_eq!(chip8.step(0xE0, 0x42), Ok(10));
+    assert_eq!(chip8.registers[0], 0x42);
+    assert!(chip8.step(0xE1, 0x42).is_err());
+}
+
+#[test]
+fn shifts_put_shifted_out_bit_in_vf() {
+    let mut chip8 = Chip8::new();
+    for (value, shr, shr_vf, shl, shl_vf) in [
+        (0x80, 0x40, 0, 0x00, 1),
+        (0x01, 0x00, 1, 0x02, 0),
+        (0xFF, 0x7F, 1, 0xFE, 1),
+    ] {
+        chip8.registers[1] = value;
+        chip8.step(0x81, 0x06).unwrap();
+        assert_eq!((chip8.registers[1], chip8.registers[0xf]), (shr, shr_vf), "SHR 0x{:02X}", value);
+
+        chip8.registers[1] = value;
+        chip8.step(0x81, 0x0E).unwrap();
+        assert_eq!((chip8.registers[1], chip8.registers[0xf]), (shl, shl_vf), "SHL 0x{:02X}", value);
+    }
+}
+
+#[test]
+fn register_access_is_bounds_checked() {
+    let mut chip8 = Chip8::new();
+    assert!(chip8.set_register(0xF, 0x42));
+    assert_eq!(chip8.register(0xF), Some(0x42));
+    assert!(!chip8.set_register(16, 0x42));
+    assert_eq!(chip8.register(16), None);
+}
+
+#[test]
+fn pitch_maps_to_xochip_frequency() {
+    let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+    assert!(close(chip8::pitch_frequency(64), 4000.0));
+    assert!(close(chip8::pitch_frequency(112), 8000.0));
+    assert!(close(chip8::pitch_frequency(16), 2000.0));
+    assert!(close(chip8::pitch_frequency(0), 1587.40));
+
+    let mut chip8 = Chip8::new();
+    assert!(close(chip8.beep_frequency(), 440.0));
+    chip8.registers[2] = 112;
+    chip8.step(0xF2, 0x3A).unwrap();
+    assert!(close(chip8.beep_frequency(), 8000.0));
+}
+
+#[test]
+fn default_font_can_be_left_out() {
+    let chip8 = Chip8::new();
+    assert_eq!(&chip8.memory[0..5], &[0xF0, 0x90, 0x90, 0x90, 0xF0]);
+
+    let mut chip8 = Chip8::new_with_config(chip8::Chip8Config {
+        load_default_font: false,
+        ..chip8::Chip8Config::default()
+    });
+    assert!(chip8.memory[0..0x200].iter().all(|&byte| byte == 0));
+    chip8.reset();
+    assert!(chip8.memory[0..0x200].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn current_opcode_reads_big_endian_at_pc() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0xA1, 0x23, 0xFF, 0xFF]).unwrap();
+    assert_eq!(chip8.current_opcode(), Ok(0xA123));
+    chip8.tick().unwrap();
+    assert_eq!(chip8.current_opcode(), Ok(0xFFFF));
+    assert_eq!(chip8.tick(), Err("Invalid op ffff".to_string()));
+    chip8.pc = 0xFFF;
+    assert!(chip8.current_opcode().is_err());
+}
+
+#[test]
+fn running_off_the_end_of_memory_errors_on_the_next_fetch() {
+    let mut chip8 = Chip8::new();
+    // 0xFFE: LD V0, 0x42
+    chip8.memory[0xFFE] = 0x60;
+    chip8.memory[0xFFF] = 0x42;
+    chip8.pc = 0xFFE;
+    chip8.tick().unwrap();
+    assert_eq!(chip8.registers[0], 0x42);
+    assert_eq!(chip8.pc, 0x1000);
+    assert_eq!(chip8.tick(), Err("pc out of memory bounds".to_string()));
+    assert_eq!(chip8.pc, 0x1000);
+
+    // 0xFFE: JP 0x200
+    chip8.memory[0xFFE] = 0x12;
+    chip8.memory[0xFFF] = 0x00;
+    chip8.pc = 0xFFE;
+    chip8.tick().unwrap();
+    assert_eq!(chip8.pc, 0x200);
+
+    // Only one byte is left at 0xFFF
+    chip8.pc = 0xFFF;
+    assert_eq!(chip8.tick(), Err("pc out of memory bounds".to_string()));
+}
+
+#[test]
+fn xochip_instruction_at_the_last_word_errors_instead_of_overflowing() {
+    for rewind in [0, 4] {
+        let mut chip8 = Chip8::new();
+        chip8.set_xochip(true);
+        chip8.enable_rewind(rewind);
+        // 0xFFFE: LD V0, 0x42
+        chip8.memory[0xFFFE] = 0x60;
+        chip8.memory[0xFFFF] = 0x42;
+        chip8.pc = 0xFFFE;
+        assert_eq!(chip8.peek_instruction(), Ok((0x60, 0x42)));
+        assert_eq!(chip8.tick(), Err("pc out of memory bounds".to_string()));
+        assert_eq!(chip8.pc, 0xFFFE);
+        assert_eq!(chip8.registers[0], 0);
+    }
+}
+
+#[test]
+fn random_byte_is_masked_by_kk() {
+    let mut chip8 = Chip8::new();
+    chip8.set_rng_constant(Some(0xA5));
+    for (mask, expected) in [(0xFF, 0xA5), (0x0F, 0x05), (0xF0, 0xA0), (0x00, 0x00)] {
+        chip8.step(0xC3, mask).unwrap();
+        assert_eq!(chip8.registers[3], expected);
+        assert_eq!(chip8.last_random(), Some(0xA5));
+    }
+
+    chip8.set_rng_constant(None);
+    chip8.seed_rng(0xC0DE);
+    for _ in 0..1000 {
+        chip8.step(0xC3, 0x0F).unwrap();
+        assert!(chip8.registers[3] <= 0x0F);
+        assert_eq!(chip8.registers[3], chip8.last_random().unwrap() & 0x0F);
+    }
+}
+
+#[test]
+fn random_bytes_are_roughly_uniform() {
+    let mut chip8 = Chip8::new();
+    chip8.seed_rng(0x5EED);
+    let draws = 256 * 200;
+    let mut counts = [0usize; 256];
+    for _ in 0..draws {
+        chip8.step(0xC0, 0xFF).unwrap();
+        counts[chip8.last_random().unwrap() as usize] += 1;
+    }
+    // Chi-squared with 255 degrees of freedom, 350 is far out in the tail
+    let expected = (draws / 256) as f64;
+    let chi2: f64 = counts.iter().map(|&count| (count as f64 - expected).powi(2) / expected).sum();
+    assert!(chi2 < 350.0, "chi-squared {}", chi2);
+}
+
+#[test]
+fn strict_opcodes_reject_a_nonzero_last_nibble() {
+    let mut chip8 = Chip8::new();
+    chip8.registers[1] = 7;
+    chip8.registers[2] = 7;
+    // Lenient by default, 5121 runs as 5120
+    let pc = chip8.pc;
+    chip8.step(0x51, 0x21).unwrap();
+    assert_eq!(chip8.pc, pc + 2);
+
+    chip8.set_strict_opcodes(true);
+    let pc = chip8.pc;
+    chip8.step(0x51, 0x20).unwrap();
+    assert_eq!(chip8.pc, pc + 2);
+    chip8.step(0x91, 0x20).unwrap();
+    assert_eq!(chip8.step(0x51, 0x21), Err("Invalid op 5121".to_string()));
+    assert_eq!(chip8.step(0x91, 0x2F), Err("Invalid op 912f".to_string()));
+}
+
+#[test]
+fn xochip_long_index_load_and_skips() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.step(0xF0, 0x00), Err("Invalid op f000".to_string()));
+
+    chip8.set_xochip(true);
+    assert_eq!(chip8.memory_size(), chip8::XO_MEMORY_SIZE);
+    // pc already points past F000, at nnnn
+    let pc = chip8.pc as usize;
+    chip8.memory[pc..pc + 2].copy_from_slice(&[0xAB, 0xCD]);
+    chip8.step(0xF0, 0x00).unwrap();
+    assert_eq!(chip8.i, 0xABCD);
+    assert_eq!(chip8.pc as usize, pc + 2);
+
+    // Skipping over F000 nnnn steps over all 4 bytes, anything else over 2
+    for (next, skipped) in [(0xF0, 4), (0x60, 2)] {
+        let pc = chip8.pc as usize;
+        chip8.memory[pc..pc + 2].copy_from_slice(&[next, 0x00]);
+        chip8.registers[0] = 1;
+        chip8.step(0x30, 0x01).unwrap();
+        assert_eq!(chip8.pc as usize, pc + skipped);
+    }
+
+    chip8.i = 0xFFFE;
+    chip8.step(0xF1, 0x55).unwrap();
+    assert!(chip8.step(0xF2, 0x55).is_err());
+}
+
+#[test]
+fn xochip_audio_pattern_loads_from_i() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.step(0xF0, 0x02), Err("Invalid op f002".to_string()));
+    assert_eq!(chip8.audio_pattern(), None);
+
+    chip8.set_xochip(true);
+    for idx in 0..chip8::AUDIO_PATTERN_SIZE {
+        chip8.memory[0x300 + idx] = idx as u8 * 3;
+    }
+    chip8.i = 0x300;
+    chip8.step(0xF0, 0x02).unwrap();
+    assert_eq!(chip8.audio_pattern(), Some(&chip8.memory[0x300..0x310].try_into().unwrap()));
+    assert_eq!(chip8.i, 0x300);
+    assert!((chip8.pattern_rate() - 4000.0).abs() < 0.01);
+    chip8.registers[0] = 112;
+    chip8.step(0xF0, 0x3A).unwrap();
+    assert!((chip8.pattern_rate() - 8000.0).abs() < 0.01);
+
+    chip8.i = 0xFFF8;
+    assert!(chip8.step(0xF0, 0x02).is_err());
+    chip8.reset();
+    assert_eq!(chip8.audio_pattern(), None);
+}
+
+#[test]
+fn big_font_sits_after_the_small_one() {
+    use chip8::{BIG_FONT_ADDRESS, FONT_ADDRESS};
+
+    let mut chip8 = Chip8::new();
+    assert_eq!(BIG_FONT_ADDRESS, FONT_ADDRESS + 80);
+    chip8.registers[3] = 0x1;
+    chip8.step(0xF3, 0x30).unwrap();
+    assert_eq!(chip8.i as usize, BIG_FONT_ADDRESS + 10);
+    let digit = &chip8.memory[chip8.i as usize..][..10];
+    assert_eq!(digit, [0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF]);
+    // The high nibble is ignored, 0x0F and 0xFF both give F
+    chip8.registers[3] = 0xFF;
+    chip8.step(0xF3, 0x30).unwrap();
+    assert_eq!(chip8.i as usize, BIG_FONT_ADDRESS + 150);
+    assert_eq!(chip8::disassemble(0xF3, 0x30), "LD HF, V3");
+}
+
+#[test]
+fn rpl_flags_save_restore_and_reach_the_hook() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let saved = Rc::new(RefCell::new(vec![]));
+    let mut chip8 = Chip8::new();
+    let hook_saved = Rc::clone(&saved);
+    chip8.set_flags_hook(Box::new(move |flags| hook_saved.borrow_mut().push(*flags)));
+    for idx in 0..8 {
+        chip8.registers[idx] = 0x10 + idx as u8;
+    }
+    chip8.step(0xF7, 0x75).unwrap();
+    assert_eq!(chip8.rpl_flags()[..8], chip8.registers[..8]);
+    assert_eq!(saved.borrow().len(), 1);
+    assert_eq!(saved.borrow()[0], *chip8.rpl_flags());
+
+    // Flags outlive a reset, like the calculator's
+    chip8.reset();
+    chip8.step(0xF3, 0x85).unwrap();
+    assert_eq!(chip8.registers[..5], [0x10, 0x11, 0x12, 0x13, 0]);
+    let restored = Chip8::from_state(&chip8.save_state()).unwrap();
+    assert_eq!(restored.rpl_flags(), chip8.rpl_flags());
+
+    // SUPER-CHIP stops at V7, XO-CHIP has a flag for every register
+    assert_eq!(chip8.step(0xF8, 0x75), Err("rpl flags only hold V0-V7".to_string()));
+    assert_eq!(chip8.step(0xF8, 0x85), Err("rpl flags only hold V0-V7".to_string()));
+    chip8.set_xochip(true);
+    chip8.step(0xFF, 0x75).unwrap();
+    assert_eq!(saved.borrow().len(), 2);
+
+    chip8.set_rpl_flags(&[1, 2, 3]);
+    assert_eq!(chip8.rpl_flags()[..4], [1, 2, 3, 0]);
+}
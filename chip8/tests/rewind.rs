@@ -0,0 +1,101 @@
+use chip8::Chip8;
+
+// 0x200: LD V0, 0x12
+// 0x202: LD I, 0x300
+// 0x204: LD B, V0
+// 0x206: ADD V0, 0x01
+const PROGRAM: [u8; 8] = [0x60, 0x12, 0xA3, 0x00, 0xF0, 0x33, 0x70, 0x01];
+
+#[test]
+fn step_back_restores_registers_and_memory() {
+    let mut chip8 = Chip8::new();
+    chip8.enable_rewind(8);
+    chip8.load_rom(&PROGRAM).unwrap();
+    for _ in 0..4 {
+        chip8.tick().unwrap();
+    }
+    assert_eq!(chip8.registers[0], 0x13);
+    assert_eq!(&chip8.memory[0x300..0x303], &[0, 1, 8]);
+
+    chip8.step_back().unwrap();
+    assert_eq!(chip8.pc, 0x206);
+    assert_eq!(chip8.registers[0], 0x12);
+
+    chip8.step_back().unwrap();
+    assert_eq!(chip8.pc, 0x204);
+    assert_eq!(&chip8.memory[0x300..0x303], &[0, 0, 0]);
+}
+
+#[test]
+fn step_back_is_bounded_by_capacity() {
+    let mut chip8 = Chip8::new();
+    chip8.enable_rewind(2);
+    chip8.load_rom(&PROGRAM).unwrap();
+    for _ in 0..4 {
+        chip8.tick().unwrap();
+    }
+    chip8.step_back().unwrap();
+    chip8.step_back().unwrap();
+    assert_eq!(chip8.pc, 0x204);
+    assert_eq!(chip8.step_back(), Err("no history to step back to".to_string()));
+}
+
+#[test]
+fn step_back_restores_the_audio_pitch_and_pattern() {
+    let mut chip8 = Chip8::new();
+    chip8.set_xochip(true);
+    chip8.enable_rewind(8);
+    // 0x200: LD V0, 0x70
+    // 0x202: PITCH V0
+    // 0x204: LD I, 0x300
+    // 0x206: AUDIO
+    chip8.load_rom(&[0x60, 0x70, 0xF0, 0x3A, 0xA3, 0x00, 0xF0, 0x02]).unwrap();
+    chip8.memory[0x300..0x310].fill(0xAA);
+    for _ in 0..4 {
+        chip8.tick().unwrap();
+    }
+    assert_eq!(chip8.audio_pattern(), Some(&[0xAA; chip8::AUDIO_PATTERN_SIZE]));
+    let pitched = chip8.beep_frequency();
+
+    chip8.step_back().unwrap();
+    assert_eq!(chip8.audio_pattern(), None);
+    assert_eq!(chip8.beep_frequency(), pitched);
+
+    chip8.step_back().unwrap();
+    chip8.step_back().unwrap();
+    assert_eq!(chip8.beep_frequency(), chip8.tone_hz() as f32);
+}
+
+#[test]
+fn step_back_restores_the_rpl_flags() {
+    let mut chip8 = Chip8::new();
+    chip8.enable_rewind(8);
+    // 0x200: LD V0, 0x42
+    // 0x202: LD R, V0
+    chip8.load_rom(&[0x60, 0x42, 0xF0, 0x75]).unwrap();
+    chip8.tick().unwrap();
+    chip8.tick().unwrap();
+    assert_eq!(chip8.rpl_flags()[0], 0x42);
+
+    chip8.step_back().unwrap();
+    assert_eq!(chip8.rpl_flags()[0], 0);
+}
+
+#[test]
+fn step_back_clears_a_wait_it_steps_over() {
+    let mut chip8 = Chip8::new();
+    chip8.enable_rewind(8);
+    // 0x200: LD V0, 0x01
+    // 0x202: LD V1, K
+    chip8.load_rom(&[0x60, 0x01, 0xF1, 0x0A]).unwrap();
+    chip8.tick().unwrap();
+    chip8.tick().unwrap();
+    assert!(chip8.is_waiting_for_key());
+
+    chip8.step_back().unwrap();
+    assert!(!chip8.is_waiting_for_key());
+    assert_eq!(chip8.pc, 0x202);
+    chip8.set_keypad(1 << 7);
+    chip8.tick().unwrap();
+    assert_eq!(chip8.registers[1], 7);
+}
@@ -0,0 +1,89 @@
+use chip8::{Chip8, Quirks};
+
+const RNG_CONSTANT: u8 = 0xA5;
+
+fn run(rom: &[u8], quirks: Quirks, setup: impl FnOnce(&mut Chip8), frames: u64) -> u64 {
+    let mut chip8 = Chip8::with_quirks(quirks);
+    chip8.set_rng_constant(Some(RNG_CONSTANT));
+    chip8.load_rom(rom).unwrap();
+    setup(&mut chip8);
+    chip8.run_frames(frames).unwrap();
+    chip8.display_hash()
+}
+
+#[test]
+fn ibm_logo() {
+    let rom = include_bytes!("../../programs/IBM Logo.ch8");
+    assert_eq!(run(rom, Quirks::default(), |_| {}, 60), 0xc094f65422bd4e58);
+}
+
+#[test]
+fn corax_plus() {
+    let rom = include_bytes!("../../programs/3-corax+.ch8");
+    assert_eq!(run(rom, Quirks::default(), |_| {}, 120), 0xfd9ed7824f23f9f8);
+}
+
+#[test]
+fn flags() {
+    let rom = include_bytes!("../../programs/4-flags.ch8");
+    assert_eq!(run(rom, Quirks::default(), |_| {}, 120), 0xd00ded18b60aff33);
+}
+
+#[test]
+fn quirks_chip8() {
+    let rom = include_bytes!("../../programs/5-quirks.ch8");
+    // 0x1FF preselects the platform in the test menu, 1 being CHIP-8
+    let setup = |chip8: &mut Chip8| chip8.memory[0x1FF] = 1;
+    assert_eq!(run(rom, Quirks::chip8(), setup, 600), 0xbf58fe49c0a153fb);
+}
+
+#[test]
+fn verify_rom_reports_undecodable_words() {
+    let capacity = Chip8::rom_capacity();
+    assert!(chip8::verify_rom(&[], capacity).is_err());
+    assert!(chip8::verify_rom(&[0; 4096], capacity).is_err());
+    // 0x200: CLS
+    // 0x202: data
+    assert_eq!(chip8::verify_rom(&[0x00, 0xE0, 0xFF, 0xFF], capacity), Ok(vec![0x202]));
+
+    // An XO-CHIP machine has room for 4096 bytes of CLS
+    let mut chip8 = Chip8::new();
+    chip8.set_xochip(true);
+    assert_eq!(chip8::verify_rom(&[0x00, 0xE0].repeat(2048), chip8.capacity()), Ok(vec![]));
+}
+
+#[test]
+fn rom_hash_is_stable() {
+    assert_eq!(chip8::rom_hash(&[]), 0xcbf29ce484222325);
+    let rom = include_bytes!("../../programs/IBM Logo.ch8");
+    assert_ne!(chip8::rom_hash(rom), chip8::rom_hash(&rom[1..]));
+}
+
+#[test]
+fn oversized_rom_reports_sizes() {
+    let capacity = Chip8::rom_capacity();
+    assert_eq!(capacity, 3584);
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.free_memory(), capacity);
+    assert_eq!(
+        chip8.load_rom(&vec![0; 3600]),
+        Err("rom is 3600 bytes but only 3584 bytes are available".to_string())
+    );
+    assert_eq!(chip8.free_memory(), capacity);
+    chip8.load_rom(&[0; 100]).unwrap();
+    assert_eq!(chip8.free_memory(), capacity - 100);
+    assert!(chip8.load_rom_from(&mut &vec![0; capacity + 1][..]).is_err());
+    assert_eq!(chip8.load_rom_from(&mut &[0u8; 10][..]), Ok(10));
+    assert_eq!(chip8.free_memory(), capacity - 10);
+}
+
+#[test]
+fn xochip_roms_can_use_all_64k() {
+    let mut chip8 = Chip8::new();
+    chip8.set_xochip(true);
+    let capacity = chip8::XO_MEMORY_SIZE - 0x200;
+    assert_eq!(chip8.free_memory(), capacity);
+    chip8.load_rom(&vec![0xAA; 3600]).unwrap();
+    assert_eq!(chip8.memory[0x200 + 3599], 0xAA);
+    assert!(chip8.load_rom(&vec![0; capacity + 1]).is_err());
+}
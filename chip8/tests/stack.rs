@@ -0,0 +1,34 @@
+use chip8::Chip8;
+
+// 0x200: CALL 0x200
+const RECURSE: [u8; 2] = [0x22, 0x00];
+
+#[test]
+fn call_overflows_at_default_depth() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&RECURSE).unwrap();
+    for _ in 0..16 {
+        chip8.tick().unwrap();
+    }
+    assert_eq!(chip8.call_depth(), 16);
+    assert_eq!(chip8.tick(), Err("stack overflow".to_string()));
+    assert_eq!(chip8.call_depth(), 16);
+}
+
+#[test]
+fn call_overflows_at_configured_depth() {
+    let mut chip8 = Chip8::new();
+    chip8.set_max_stack_depth(Some(4));
+    chip8.load_rom(&RECURSE).unwrap();
+    for _ in 0..4 {
+        chip8.tick().unwrap();
+    }
+    assert_eq!(chip8.tick(), Err("stack overflow".to_string()));
+    assert_eq!(chip8.stack_snapshot(), &[0x202, 0x202, 0x202, 0x202]);
+}
+
+#[test]
+fn ret_on_empty_stack_errors() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.step(0x00, 0xEE), Err("stack underflow".to_string()));
+}
@@ -0,0 +1,71 @@
+use chip8::Chip8;
+
+#[test]
+fn save_state_round_trips() {
+    let mut chip8 = Chip8::new();
+    let rom = include_bytes!("../../programs/IBM Logo.ch8");
+    chip8.load_rom(rom).unwrap();
+    chip8.run_frames(10).unwrap();
+    chip8.stack.push(0x234);
+    chip8.delay_timer = 7;
+    let state = chip8.save_state();
+
+    let mut restored = Chip8::from_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+    assert_eq!(restored.pc, chip8.pc);
+    assert_eq!(restored.stack, vec![0x234]);
+    assert_eq!(restored.display_hash(), chip8.display_hash());
+    assert_eq!(restored.free_memory(), Chip8::rom_capacity() - rom.len());
+
+    chip8.stack.clear();
+    restored.stack.clear();
+    chip8.run_frames(50).unwrap();
+    restored.run_frames(50).unwrap();
+    assert_eq!(restored.display_hash(), chip8.display_hash());
+}
+
+#[test]
+fn load_state_rejects_bad_input() {
+    let mut chip8 = Chip8::new();
+    let state = chip8.save_state();
+    assert_eq!(chip8.load_state(b"nope"), Err("not a save state".to_string()));
+    assert_eq!(
+        chip8.load_state(&state[..state.len() - 1]),
+        Err("truncated save state".to_string()),
+    );
+    let mut longer = state.clone();
+    longer.push(0);
+    assert!(chip8.load_state(&longer).is_err());
+}
+
+#[test]
+fn save_state_keeps_the_hires_display() {
+    let mut chip8 = Chip8::new();
+    chip8.step(0x00, 0xFF).unwrap();
+    let last = chip8.display_buffer().len() - 1;
+    chip8.display[last] = 0x01;
+    let restored = Chip8::from_state(&chip8.save_state()).unwrap();
+    assert!(restored.is_hires());
+    assert!(restored.pixel(chip8::HIRES_WIDTH - 1, chip8::HIRES_HEIGHT - 1));
+    assert_eq!(restored.display_hash(), chip8.display_hash());
+}
+
+#[test]
+fn save_state_keeps_xochip_memory_and_planes() {
+    let mut chip8 = Chip8::new();
+    chip8.set_xochip(true);
+    chip8.memory[0xFFFF] = 0x42;
+    chip8.step(0xF2, 0x01).unwrap();
+    chip8.memory[0x300] = 0x80;
+    chip8.i = 0x300;
+    chip8.step(0xD0, 0x01).unwrap();
+    chip8.memory[0x310] = 0xA5;
+    chip8.i = 0x310;
+    chip8.step(0xF0, 0x02).unwrap();
+    let restored = Chip8::from_state(&chip8.save_state()).unwrap();
+    assert!(restored.is_xochip());
+    assert_eq!(restored.audio_pattern(), chip8.audio_pattern());
+    assert_eq!(restored.memory[0xFFFF], 0x42);
+    assert_eq!(restored.pixel_color(0, 0), 2);
+    assert_eq!(restored.display_hash(), chip8.display_hash());
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use chip8::{Chip8, Chip8Config, Chip8Thread, Command, Output};
+
+fn next_output(thread: &Chip8Thread) -> Output {
+    thread.outputs().recv_timeout(Duration::from_secs(5)).unwrap()
+}
+
+#[test]
+fn thread_runs_frames_and_answers_commands() {
+    let thread = Chip8Thread::spawn(Chip8Config::default());
+    // 0x200: LD F, V0
+    // 0x202: DRW V0, V0, 5
+    // 0x204: JP 0x204
+    thread.send(Command::LoadRom(vec![0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04])).unwrap();
+    let display = match next_output(&thread) {
+        Output::Frame { display, plane2, hires, beeping } => {
+            assert!(!hires && !beeping);
+            assert!(plane2.iter().all(|&byte| byte == 0));
+            display
+        }
+        output => panic!("expected a frame, got {:?}", output),
+    };
+    // The top of the 0 glyph
+    assert_eq!(display[0], 0xF0);
+
+    thread.send(Command::Pause(true)).unwrap();
+    thread.send(Command::SaveState).unwrap();
+    let state = loop {
+        match next_output(&thread) {
+            Output::State(state) => break state,
+            Output::Frame { .. } => {}
+            output => panic!("expected a state, got {:?}", output),
+        }
+    };
+    let restored = Chip8::from_state(&state).unwrap();
+    assert_eq!(restored.display, *display);
+    assert_eq!(restored.pc, 0x204);
+
+    thread.send(Command::LoadRom(vec![0; 4096])).unwrap();
+    assert!(matches!(next_output(&thread), Output::Error(_)));
+}
+
+#[test]
+fn frames_carry_the_xochip_second_plane() {
+    let thread = Chip8Thread::spawn(Chip8Config { xochip: true, ..Chip8Config::default() });
+    // 0x200: PLANE 2
+    // 0x202: LD F, V0
+    // 0x204: DRW V0, V0, 5
+    // 0x206: JP 0x206
+    thread.send(Command::LoadRom(vec![0xF2, 0x01, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06])).unwrap();
+    match next_output(&thread) {
+        Output::Frame { display, plane2, .. } => {
+            assert_eq!(display[0], 0x00);
+            assert_eq!(plane2[0], 0xF0);
+        }
+        output => panic!("expected a frame, got {:?}", output),
+    }
+}
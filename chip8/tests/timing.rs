@@ -0,0 +1,320 @@
+use chip8::Chip8;
+
+fn ldbx_cycles(value: u8) -> usize {
+    let mut chip8 = Chip8::new();
+    chip8.registers[0] = value;
+    chip8.i = 0x300;
+    chip8.step(0xF0, 0x33).unwrap()
+}
+
+#[test]
+fn bcd_cycles_depend_on_digit_sum() {
+    assert_eq!(ldbx_cycles(0), 364);
+    assert_eq!(ldbx_cycles(7), 364 + 7 * 73);
+    assert_eq!(ldbx_cycles(100), 364 + 73);
+    // 2 + 5 + 5
+    assert_eq!(ldbx_cycles(255), 1240);
+}
+
+#[test]
+fn bulk_transfer_cycles_depend_on_register_count() {
+    let mut chip8 = Chip8::new();
+    chip8.i = 0x300;
+    assert_eq!(chip8.step(0xF0, 0x55).unwrap(), 128);
+    assert_eq!(chip8.step(0xF7, 0x55).unwrap(), 576);
+    assert_eq!(chip8.step(0xFF, 0x65).unwrap(), 1088);
+}
+
+#[test]
+fn run_cycles_stops_once_budget_is_spent() {
+    let mut chip8 = Chip8::new();
+    // 0x200: LD V0, 0x01 repeated
+    chip8.load_rom(&[0x60, 0x01, 0x60, 0x01, 0x60, 0x01, 0x60, 0x01]).unwrap();
+    chip8.delay_timer = 5;
+    let spent = chip8.run_cycles(1).unwrap();
+    assert_eq!(chip8.pc, 0x202);
+    let spent_again = chip8.run_cycles(spent + 1).unwrap();
+    assert_eq!(spent_again, 2 * spent);
+    assert_eq!(chip8.pc, 0x206);
+    assert_eq!(chip8.delay_timer, 5);
+}
+
+#[test]
+fn step_n_stops_at_breakpoints() {
+    let mut chip8 = Chip8::new();
+    // 0x200: LD V0, 0x01 repeated
+    chip8.load_rom(&[0x60, 0x01, 0x60, 0x01, 0x60, 0x01, 0x60, 0x01]).unwrap();
+    chip8.step_n(2).unwrap();
+    assert_eq!(chip8.pc, 0x204);
+    assert!(!chip8.at_breakpoint());
+
+    chip8.add_breakpoint(0x206);
+    chip8.step_n(10).unwrap();
+    assert_eq!(chip8.pc, 0x206);
+    assert!(chip8.at_breakpoint());
+
+    // Stepping again moves off the breakpoint
+    chip8.step_n(1).unwrap();
+    assert_eq!(chip8.pc, 0x208);
+}
+
+#[test]
+fn frame_reports_waiting_for_key() {
+    use chip8::{FrameOutcome, Key};
+
+    let mut chip8 = Chip8::new();
+    // 0x200: LD V0, K
+    // 0x202: EXIT
+    chip8.load_rom(&[0xF0, 0x0A, 0x00, 0xFD]).unwrap();
+    assert_eq!(chip8.frame(), Ok(FrameOutcome::WaitingForKey));
+    assert!(chip8.is_waiting_for_key());
+    chip8.press_key(Key::K7);
+    chip8.press_key(Key::KA);
+    chip8.release_key(Key::KA);
+    assert_eq!(chip8.pressed_keys().collect::<Vec<_>>(), [Key::K7]);
+    assert_eq!(chip8.frame(), Ok(FrameOutcome::Halted));
+    assert_eq!(chip8.registers[0], 7);
+}
+
+#[test]
+fn self_jump_is_reported_as_idle() {
+    use chip8::FrameOutcome;
+
+    let mut chip8 = Chip8::new();
+    // 0x200: JP 0x200
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    assert_eq!(chip8.frame(), Ok(FrameOutcome::Completed));
+    chip8.set_detect_halt_loop(true);
+    let before = chip8.instruction_count();
+    assert_eq!(chip8.frame(), Ok(FrameOutcome::Idle));
+    assert_eq!(chip8.instruction_count() - before, 1);
+}
+
+#[test]
+fn unchanged_display_is_reported_as_idle() {
+    use chip8::FrameOutcome;
+
+    let mut chip8 = Chip8::new();
+    chip8.set_detect_halt_loop(true);
+    chip8.set_idle_frame_threshold(3);
+    // 0x200: LD V0, 0x00
+    // 0x202: JP 0x200
+    chip8.load_rom(&[0x60, 0x00, 0x12, 0x00]).unwrap();
+    let outcomes: Vec<_> = (0..4).map(|_| chip8.frame().unwrap()).collect();
+    assert_eq!(outcomes[..3], [FrameOutcome::Completed; 3]);
+    assert_eq!(outcomes[3], FrameOutcome::Idle);
+}
+
+#[test]
+fn skipped_errors_are_collected_without_ending_the_frame() {
+    use chip8::{FrameOutcome, InvalidOpPolicy};
+
+    let mut chip8 = Chip8::new();
+    chip8.set_invalid_op_policy(InvalidOpPolicy::SkipAsNop);
+    // 0x200: invalid
+    // 0x202: RET with an empty stack
+    // 0x204: LD V0, 0x2A
+    // 0x206: EXIT
+    chip8.load_rom(&[0xFF, 0xFF, 0x00, 0xEE, 0x60, 0x2A, 0x00, 0xFD]).unwrap();
+    assert_eq!(chip8.frame(), Ok(FrameOutcome::Halted));
+    assert_eq!(chip8.registers[0], 0x2A);
+    assert_eq!(
+        chip8.drain_diagnostics(),
+        ["0x200: skipped invalid op ffff", "0x202: stack underflow"]
+    );
+    assert!(chip8.drain_diagnostics().is_empty());
+}
+
+#[test]
+fn instructions_per_frame_ignores_cycle_costs() {
+    use chip8::Chip8Config;
+
+    let mut chip8 = Chip8::new_with_config(Chip8Config {
+        instructions_per_frame: Some(11),
+        ..Chip8Config::default()
+    });
+    // Cheap and expensive ops alike
+    // 0x200: LD V0, 0x00
+    // 0x202: LD F, V0
+    // 0x204: LD B, V0
+    // 0x206: JP 0x200
+    chip8.load_rom(&[0x60, 0x00, 0xF0, 0x29, 0xF0, 0x33, 0x12, 0x00]).unwrap();
+    for frame in 1..=3 {
+        chip8.frame().unwrap();
+        assert_eq!(chip8.instruction_count(), frame * 11);
+    }
+    chip8.set_instructions_per_frame(None);
+    chip8.frame().unwrap();
+    assert_ne!(chip8.instruction_count(), 4 * 11);
+}
+
+#[test]
+fn frozen_timers_only_move_when_decremented() {
+    let mut chip8 = Chip8::new();
+    // 0x200: JP 0x200
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    chip8.delay_timer = 10;
+    chip8.sound_timer = 1;
+    chip8.set_freeze_timers(true);
+    chip8.run_frames(3).unwrap();
+    assert_eq!((chip8.delay_timer, chip8.sound_timer), (10, 1));
+    chip8.decrement_timers();
+    chip8.decrement_timers();
+    assert_eq!((chip8.delay_timer, chip8.sound_timer), (8, 0));
+    chip8.set_freeze_timers(false);
+    chip8.frame().unwrap();
+    assert_eq!(chip8.delay_timer, 7);
+}
+
+// 0x200: CLS
+// 0x202: DRW V0, V1, 1
+// 0x204: JP 0x204
+fn cls_then_draw(quirks: chip8::Quirks) -> Chip8 {
+    let mut chip8 = Chip8::with_quirks(quirks);
+    chip8.load_rom(&[0x00, 0xE0, 0xD0, 0x11, 0x12, 0x04]).unwrap();
+    chip8.memory[0x300] = 0xFF;
+    chip8.i = 0x300;
+    chip8
+}
+
+#[test]
+fn cls_followed_by_draw_under_display_wait() {
+    use chip8::Quirks;
+
+    // The clear does not count as a draw, both land in the first frame
+    let mut chip8 = cls_then_draw(Quirks { display_wait: true, ..Quirks::default() });
+    chip8.frame().unwrap();
+    assert_eq!(chip8.display[0], 0xFF);
+    assert_eq!(chip8.pc, 0x204);
+
+    // The clear takes the first frame and the draw waits for the next one
+    let mut chip8 = cls_then_draw(Quirks { display_wait: true, cls_waits: true, ..Quirks::default() });
+    chip8.frame().unwrap();
+    assert_eq!(chip8.display[0], 0);
+    assert_eq!(chip8.pc, 0x202);
+    chip8.frame().unwrap();
+    assert_eq!(chip8.display[0], 0xFF);
+    assert_eq!(chip8.pc, 0x204);
+}
+
+#[test]
+fn second_draw_in_a_frame_waits_for_the_next_one() {
+    use chip8::Quirks;
+
+    let mut chip8 = Chip8::with_quirks(Quirks { display_wait: true, ..Quirks::default() });
+    // 0x200: DRW V0, V1, 1
+    // 0x202: DRW V0, V1, 1
+    // 0x204: JP 0x204
+    chip8.load_rom(&[0xD0, 0x11, 0xD0, 0x11, 0x12, 0x04]).unwrap();
+    chip8.memory[0x300] = 0xFF;
+    chip8.i = 0x300;
+    chip8.frame().unwrap();
+    assert_eq!(chip8.display[0], 0xFF);
+    assert_eq!(chip8.pc, 0x202);
+    chip8.frame().unwrap();
+    assert_eq!(chip8.display[0], 0);
+    assert_eq!(chip8.pc, 0x204);
+}
+
+#[test]
+fn cls_only_resets_vf_when_asked_to() {
+    use chip8::Quirks;
+
+    for cls_resets_vf in [false, true] {
+        let mut chip8 = Chip8::with_quirks(Quirks { cls_resets_vf, ..Quirks::default() });
+        chip8.registers[0xf] = 1;
+        chip8.step(0x00, 0xE0).unwrap();
+        assert_eq!(chip8.registers[0xf], !cls_resets_vf as u8);
+    }
+}
+
+#[test]
+fn fractional_timers_never_get_ahead_of_the_decrement() {
+    let mut chip8 = Chip8::new();
+    // 0x200: JP 0x200
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    chip8.delay_timer = 255;
+    chip8.sound_timer = 1;
+    chip8.advance_timer_phase(0.5);
+    assert_eq!(chip8.delay_timer_fractional(), 254.5);
+    assert_eq!(chip8.sound_timer_fractional(), 0.5);
+    // Late frames hold just above the next value
+    chip8.advance_timer_phase(3.0);
+    assert!(chip8.delay_timer_fractional() > 254.0);
+    assert!(chip8.sound_timer_fractional() > 0.0);
+    chip8.frame().unwrap();
+    assert_eq!(chip8.delay_timer_fractional(), 254.0);
+    assert_eq!(chip8.sound_timer_fractional(), 0.0);
+    chip8.advance_timer_phase(0.25);
+    assert_eq!(chip8.sound_timer_fractional(), 0.0);
+
+    chip8.set_freeze_timers(true);
+    chip8.decrement_timers();
+    chip8.advance_timer_phase(0.5);
+    assert_eq!(chip8.delay_timer_fractional(), 253.0);
+}
+
+#[test]
+fn info_sums_up_the_machine() {
+    use chip8::Quirks;
+
+    let mut chip8 = Chip8::with_quirks(Quirks::schip());
+    // 0x200: CALL 0x204
+    // 0x202: EXIT
+    // 0x204: LD V0, K
+    chip8.load_rom(&[0x22, 0x04, 0x00, 0xFD, 0xF0, 0x0A]).unwrap();
+    chip8.frame().unwrap();
+    let info = chip8.info();
+    assert_eq!((info.width, info.height), (chip8::DISPLAY_WIDTH, chip8::DISPLAY_HEIGHT));
+    assert_eq!(info.quirks, Quirks::schip());
+    assert_eq!(info.cycles_per_frame, chip8.cycles_per_frame());
+    assert_eq!(info.instructions_per_frame, None);
+    assert!(info.waiting_for_key && !info.halted);
+    assert_eq!(info.instruction_count, chip8.instruction_count());
+    assert_eq!(info.frame_count, 1);
+    assert_eq!(info.stack_depth, 1);
+
+    chip8.step(0x00, 0xFF).unwrap();
+    let info = chip8.info();
+    assert_eq!((info.width, info.height), (chip8::HIRES_WIDTH, chip8::HIRES_HEIGHT));
+}
+
+#[test]
+fn queued_presses_each_get_a_frame() {
+    use chip8::Key;
+
+    let mut chip8 = Chip8::new();
+    chip8.set_instructions_per_frame(Some(1));
+    // 0x200: LD V0, K
+    // 0x202: JP 0x200
+    chip8.load_rom(&[0xF0, 0x0A, 0x12, 0x00]).unwrap();
+    // Down and up again before the frame, a plain keypad update misses it
+    chip8.queue_key_press(Key::K5);
+    chip8.queue_key_press(Key::K5);
+    chip8.queue_key_press(Key::K9);
+    let mut seen = vec![];
+    for _ in 0..6 {
+        chip8.frame().unwrap();
+        seen.push(if chip8.is_waiting_for_key() { None } else { Some(chip8.registers[0]) });
+        // Fx0A again for the next press
+        chip8.pc = 0x200;
+    }
+    // The second 5 is let up for a frame first, the 9 gets its own frame
+    // right after it
+    assert_eq!(seen, [Some(5), None, Some(5), Some(9), None, None]);
+    assert_eq!(chip8.keypad(), 0);
+}
+
+#[test]
+fn checked_cycle_costs_reject_free_instructions() {
+    let mut chip8 = Chip8::new();
+    chip8.set_check_cycle_costs(true);
+    // Costly but sane, and Fx0A spending the rest of the frame
+    assert_eq!(chip8.step(0xD0, 0x05), Ok(22734));
+    assert_eq!(chip8.step(0xF0, 0x0A), Ok(chip8.cycles_per_frame()));
+    // A custom opcode that forgot its cost would spin run_cycles forever
+    chip8.set_custom_opcode_handler(Box::new(|_, _, _| Some(0)));
+    assert_eq!(chip8.step(0xFF, 0xFF), Err("op ffff cost 0 cycles".to_string()));
+    chip8.set_check_cycle_costs(false);
+    assert_eq!(chip8.step(0xFF, 0xFF), Ok(0));
+}
@@ -0,0 +1,45 @@
+#![no_main]
+
+use chip8::{Chip8, Quirks};
+use libfuzzer_sys::fuzz_target;
+
+// Input layout: quirk bits, registers, pc, i, timers, keypad, stack depth and
+// stack entries, then opcode pairs until the end of the input
+const HEADER_SIZE: usize = 2 + 16 + 2 + 2 + 2 + 2 + 1;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < HEADER_SIZE {
+        return;
+    }
+    let (header, rest) = data.split_at(HEADER_SIZE);
+    let bits = u16::from_be_bytes([header[0], header[1]]);
+    let mut chip8 = Chip8::with_quirks(Quirks {
+        shift_vy: bits & 0x01 != 0,
+        wrap_x: bits & 0x02 != 0,
+        wrap_y: bits & 0x80 != 0,
+        vf_reset: bits & 0x04 != 0,
+        increment_i: bits & 0x08 != 0,
+        jump_vx: bits & 0x10 != 0,
+        wrap_i: bits & 0x20 != 0,
+        fx1e_sets_vf: bits & 0x40 != 0,
+        display_wait: bits & 0x100 != 0,
+        cls_waits: bits & 0x200 != 0,
+        cls_resets_vf: bits & 0x400 != 0,
+    });
+    chip8.set_xochip(bits & 0x800 != 0);
+    chip8.registers.copy_from_slice(&header[2..18]);
+    chip8.pc = u16::from_be_bytes([header[18], header[19]]);
+    chip8.i = u16::from_be_bytes([header[20], header[21]]);
+    chip8.delay_timer = header[22];
+    chip8.sound_timer = header[23];
+    chip8.keypad = u16::from_be_bytes([header[24], header[25]]);
+    let depth = (header[26] as usize % 32).min(rest.len() / 2);
+    let (stack, ops) = rest.split_at(depth * 2);
+    for addr in stack.chunks_exact(2) {
+        chip8.stack.push(u16::from_be_bytes([addr[0], addr[1]]));
+    }
+    for op in ops.chunks_exact(2) {
+        // Errors are fine, only panics are bugs
+        let _ = chip8.execute_raw(op[0], op[1]);
+    }
+});
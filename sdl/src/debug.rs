@@ -0,0 +1,88 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+use chip8::Chip8;
+
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+pub const TEXT_SCALE: u32 = 3;
+const CELL_WIDTH: u32 = (GLYPH_WIDTH + 1) * TEXT_SCALE;
+const CELL_HEIGHT: u32 = (GLYPH_HEIGHT + 1) * TEXT_SCALE;
+const MARGIN: u32 = 8;
+const UPCOMING_INSTRUCTIONS: u16 = 5;
+
+pub fn draw_text(canvas: &mut Canvas<Window>, x: i32, y: i32, text: &str) -> Result<(), String> {
+    for (char_idx, c) in text.chars().enumerate() {
+        let char_x = x + (char_idx as u32 * CELL_WIDTH) as i32;
+        for (row_idx, row) in font::glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if row >> (GLYPH_WIDTH - 1 - col) & 1 != 0 {
+                    canvas.fill_rect(Rect::new(
+                        char_x + (col * TEXT_SCALE) as i32,
+                        y + (row_idx as u32 * TEXT_SCALE) as i32,
+                        TEXT_SCALE,
+                        TEXT_SCALE,
+                    ))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn overlay_lines(chip8: &Chip8) -> Vec<String> {
+    let mut lines = vec![format!(
+        "PC {:04X}  I {:04X}  DT {:02X}  ST {:02X}",
+        chip8.pc, chip8.i, chip8.delay_timer, chip8.sound_timer,
+    )];
+    for (half_idx, registers) in chip8.registers.chunks(8).enumerate() {
+        let line: Vec<String> = registers
+            .iter()
+            .enumerate()
+            .map(|(idx, v)| format!("V{:X} {:02X}", half_idx * 8 + idx, v))
+            .collect();
+        lines.push(line.join(" "));
+    }
+    let stack: Vec<String> = chip8.stack.iter().map(|addr| format!("{:04X}", addr)).collect();
+    lines.push(format!("STACK {}", if stack.is_empty() { "-".to_string() } else { stack.join(" ") }));
+    lines.push(String::new());
+    for idx in 0..UPCOMING_INSTRUCTIONS {
+        let addr = chip8.pc as usize + idx as usize * 2;
+        if addr + 1 >= chip8.memory_size() {
+            break;
+        }
+        lines.push(format!(
+            "{} {:04X} {}",
+            if idx == 0 { ">" } else { " " },
+            addr,
+            chip8::disassemble(chip8.memory[addr], chip8.memory[addr + 1]),
+        ));
+    }
+    lines.push(String::new());
+    lines.push("F5 RESUME  F6 STEP  F7 TIMERS".to_string());
+    lines
+}
+
+pub fn draw_overlay(canvas: &mut Canvas<Window>, chip8: &Chip8) -> Result<(), String> {
+    let lines = overlay_lines(chip8);
+    let width = lines.iter().map(|line| line.len()).max().unwrap_or(0) as u32 * CELL_WIDTH;
+    let height = lines.len() as u32 * CELL_HEIGHT;
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 176));
+    canvas.fill_rect(Rect::new(0, 0, width + MARGIN * 2, height + MARGIN * 2))?;
+    canvas.set_draw_color(Color::RGB(255, 200, 0));
+    for (line_idx, line) in lines.iter().enumerate() {
+        draw_text(
+            canvas,
+            MARGIN as i32,
+            (MARGIN + line_idx as u32 * CELL_HEIGHT) as i32,
+            line,
+        )?;
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    Ok(())
+}
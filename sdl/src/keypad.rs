@@ -0,0 +1,79 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use chip8::Key;
+
+use crate::debug::{self, TEXT_SCALE};
+use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+
+const CELL_WIDTH: u32 = 96;
+const CELL_HEIGHT: u32 = 48;
+const CELL_GAP: u32 = 4;
+
+pub const STRIP_HEIGHT: u32 = CELL_HEIGHT * 4;
+
+// Clickable hex keypad drawn in a strip below the display
+pub struct TouchKeypad {
+    x: i32,
+    y: i32,
+}
+
+impl TouchKeypad {
+    pub fn new(window_width: u32, display_height: u32) -> Self {
+        Self {
+            x: (window_width as i32 - (CELL_WIDTH * 4) as i32) / 2,
+            y: display_height as i32,
+        }
+    }
+
+    fn cell(&self, row: usize, col: usize) -> Rect {
+        Rect::new(
+            self.x + (col as u32 * CELL_WIDTH + CELL_GAP / 2) as i32,
+            self.y + (row as u32 * CELL_HEIGHT + CELL_GAP / 2) as i32,
+            CELL_WIDTH - CELL_GAP,
+            CELL_HEIGHT - CELL_GAP,
+        )
+    }
+
+    // Hex key under the given window coordinates
+    pub fn key_at(&self, x: i32, y: i32) -> Option<u8> {
+        for (row, keys) in chip8::KEYPAD_LAYOUT.iter().enumerate() {
+            for (col, key) in keys.iter().enumerate() {
+                if self.cell(row, col).contains_point((x, y)) {
+                    return Some(*key);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, pressed: &[Key]) -> Result<(), String> {
+        for (row, keys) in chip8::KEYPAD_LAYOUT.iter().enumerate() {
+            for (col, key) in keys.iter().enumerate() {
+                let cell = self.cell(row, col);
+                let pressed = pressed.iter().any(|down| down.index() == *key);
+                canvas.set_draw_color(if pressed {
+                    Color::RGB(255, 200, 0)
+                } else {
+                    Color::RGB(48, 48, 48)
+                });
+                canvas.fill_rect(cell)?;
+                canvas.set_draw_color(if pressed {
+                    Color::RGB(0, 0, 0)
+                } else {
+                    Color::RGB(255, 255, 255)
+                });
+                debug::draw_text(
+                    canvas,
+                    cell.center().x() - (GLYPH_WIDTH * TEXT_SCALE / 2) as i32,
+                    cell.center().y() - (GLYPH_HEIGHT * TEXT_SCALE / 2) as i32,
+                    &format!("{:X}", key),
+                )?;
+            }
+        }
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        Ok(())
+    }
+}
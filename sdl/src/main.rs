@@ -1,216 +1,1041 @@
-use std::{env, fs};
-use std::io::{self, Read};
-use std::time::{Duration, Instant};
-
-use sdl2::audio::{AudioCallback, AudioSpecDesired};
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
-
-use chip8::Chip8;
-
-#[derive(Debug)]
-pub enum FrontError {
-    Chip8(String),
-    Io(io::Error),
-}
-
-impl From<io::Error> for FrontError {
-    fn from(err: io::Error) -> Self {
-        Self::Io(err)
-    }
-}
-
-impl From<String> for FrontError {
-    fn from(err: String) -> Self {
-        Self::Chip8(err)
-    }
-}
-
-
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
-}
-
-impl AudioCallback for SquareWave {
-    type Channel = f32;
-
-    fn callback(&mut self, out: &mut [f32]) {
-        // Generate a square wave
-        for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
-        }
-    }
-}
-
-fn main() -> Result<(), FrontError> {
-    let argv: Vec<_> = env::args().collect();
-    if argv.len() != 2 {
-        println!("Usage: {} <program_path>", &argv[0]);
-        return Ok(());
-    }
-    let mut rom: Vec<u8> = Vec::new();
-    fs::OpenOptions::new()
-        .read(true)
-        .open(&argv[1])?
-        .read_to_end(&mut rom)?;
-    let mut chip8 = Chip8::new();
-    chip8.load_rom(&rom)?;
-
-    let sdl_context = sdl2::init()?;
-    let audio_subsystem = sdl_context.audio()?;
-
-    let desired_spec = AudioSpecDesired {
-        freq: Some(44100),
-        channels: Some(1), // mono
-        samples: None,     // default sample size
-    };
-
-    let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
-        // initialize the audio callback
-        SquareWave {
-            phase_inc: 440.0 / spec.freq as f32,
-            phase: 0.0,
-            volume: 0.25,
-        }
-    })?;
-
-    let video_subsystem = sdl_context.video()?;
-    let window = video_subsystem
-        .window(
-            "chip8",
-            chip8::DISPLAY_WIDTH as u32 * 16,
-            chip8::DISPLAY_HEIGHT as u32 * 16,
-        )
-        .position_centered()
-        .opengl()
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
-    canvas.clear();
-    canvas.present();
-
-    let texture_creator = canvas.texture_creator();
-    let mut tex_display = texture_creator
-        .create_texture_streaming(
-            PixelFormatEnum::RGB24,
-            chip8::DISPLAY_WIDTH as u32,
-            chip8::DISPLAY_HEIGHT as u32,
-        )
-        .map_err(|e| e.to_string())?;
-
-    let frame_duration = Duration::new(0, 1_000_000_000u32 / 60);
-    let mut timestamp = Instant::now();
-
-    let mut event_pump = sdl_context.event_pump()?;
-
-    let mut keypad: u16 = 0u16;
-
-    'main: loop {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => {
-                    break 'main;
-                },
-                Event::KeyDown {
-                    keycode: Some(keycode),
-                    ..
-                } => {
-                    keypad |= match keycode {
-                        Keycode::Num1 => 1 << 0x1,
-                        Keycode::Num2 => 1 << 0x2,
-                        Keycode::Num3 => 1 << 0x3,
-                        Keycode::Num4 => 1 << 0xC,
-                        Keycode::Q => 1 << 0x4,
-                        Keycode::W => 1 << 0x5,
-                        Keycode::E => 1 << 0x6,
-                        Keycode::R => 1 << 0xD,
-                        Keycode::A => 1 << 0x7,
-                        Keycode::S => 1 << 0x8,
-                        Keycode::D => 1 << 0x9,
-                        Keycode::F => 1 << 0xE,
-                        Keycode::Z => 1 << 0xA,
-                        Keycode::X => 1 << 0x0,
-                        Keycode::C => 1 << 0xB,
-                        Keycode::V => 1 << 0xF,
-                        _ => 0,
-                    };
-                }
-                Event::KeyUp {
-                    keycode: Some(keycode),
-                    ..
-                } => {
-                    keypad &= !match keycode {
-                        Keycode::Num1 => 1 << 0x1,
-                        Keycode::Num2 => 1 << 0x2,
-                        Keycode::Num3 => 1 << 0x3,
-                        Keycode::Num4 => 1 << 0xC,
-                        Keycode::Q => 1 << 0x4,
-                        Keycode::W => 1 << 0x5,
-                        Keycode::E => 1 << 0x6,
-                        Keycode::R => 1 << 0xD,
-                        Keycode::A => 1 << 0x7,
-                        Keycode::S => 1 << 0x8,
-                        Keycode::D => 1 << 0x9,
-                        Keycode::F => 1 << 0xE,
-                        Keycode::Z => 1 << 0xA,
-                        Keycode::X => 1 << 0x0,
-                        Keycode::C => 1 << 0xB,
-                        Keycode::V => 1 << 0xF,
-                        _ => 0,
-                    };
-                }
-                _ => {}
-            }
-        }
-
-        if chip8.sound_timer > 0 {
-            device.resume();
-        } else {
-            device.pause();
-        }
-        chip8.keypad = keypad;
-
-        chip8.frame()?;
-
-        tex_display.with_lock(None, |buffer: &mut [u8], _pitch: usize| {
-            for display_idx in 0..chip8::DISPLAY_SIZE {
-                let byte = chip8.display[display_idx];
-                for byte_idx in 0..8 {
-                    let lit = (byte) >> byte_idx & 1 != 0;
-                    let buffer_idx = (display_idx * 8 + (7 - byte_idx)) * 3;
-                    let color = if lit {
-                        255
-                    } else {
-                        0
-                    };
-                    buffer[buffer_idx] = color;
-                    buffer[buffer_idx + 1] = color;
-                    buffer[buffer_idx + 2] = color;
-                }
-            }
-        })?;
-
-        canvas.clear();
-        canvas.copy(&tex_display, None, None)?;
-        canvas.present();
-
-        let now = Instant::now();
-        let sleep_dur = frame_duration
-            .checked_sub(now.saturating_duration_since(timestamp))
-            .unwrap_or(Duration::new(0, 0));
-        std::thread::sleep(sleep_dur);
-        timestamp = now;
-    }
-
-    Ok(())
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{Arg, Command};
+use sdl2::audio::{AudioCallback, AudioSpecDesired, AudioStatus};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+use chip8::{Chip8, Chip8Config, FrameOutcome, InvalidOpPolicy, Quirks};
+
+use crate::profiles::Profiles;
+use crate::session::Session;
+
+mod debug;
+mod font;
+mod keypad;
+mod profiles;
+mod session;
+
+#[derive(Debug)]
+pub enum FrontError {
+    Chip8(String),
+    Io(io::Error),
+    // SDL could not be set up, e.g. no display to open a window on
+    Sdl(String),
+}
+
+impl From<io::Error> for FrontError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<String> for FrontError {
+    fn from(err: String) -> Self {
+        Self::Chip8(err)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl Waveform {
+    // Sample in [-1, 1] at the given phase in [0, 1)
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => if phase <= 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+struct Beep {
+    waveform: Waveform,
+    // An XO-CHIP pattern replaces the waveform, one loop through its bits
+    // is one period
+    pattern: Option<[u8; chip8::AUDIO_PATTERN_SIZE]>,
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+}
+
+impl Beep {
+    fn update(&mut self, chip8: &Chip8, freq: i32) {
+        self.pattern = chip8.audio_pattern().copied();
+        self.phase_inc = match self.pattern {
+            Some(_) => chip8.pattern_rate() / (chip8::AUDIO_PATTERN_SIZE * 8) as f32,
+            None => chip8.beep_frequency(),
+        } / freq as f32;
+    }
+}
+
+impl AudioCallback for Beep {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            let sample = match &self.pattern {
+                Some(pattern) => {
+                    let bit = (self.phase * (pattern.len() * 8) as f32) as usize;
+                    if pattern[bit / 8] >> (7 - bit % 8) & 1 != 0 { 1.0 } else { -1.0 }
+                }
+                None => self.waveform.sample(self.phase),
+            };
+            *x = sample * self.volume;
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+
+const VOLUME_STEP: f32 = 0.05;
+const CRASH_LOG_PATH: &str = "chip8-crash.log";
+const BEEP_BORDER: u32 = 16;
+const DISPLAY_SCALE: u32 = 16;
+const SCANLINE_ALPHA: u8 = 96;
+const VIGNETTE_STEPS: u32 = 8;
+const VIGNETTE_WIDTH: u32 = 6;
+const VIGNETTE_ALPHA: u8 = 48;
+const TURBO_FRAMES: usize = 5;
+// Brightness of each XO-CHIP color, plane 1 alone stays fully lit so
+// single plane roms look the same
+const COLOR_INTENSITIES: [f32; 4] = [0.0, 1.0, 0.45, 0.7];
+// Built with --features embedded-rom and CHIP8_EMBEDDED_ROM set to the
+// absolute path of a rom, which then runs when no path is given
+#[cfg(feature = "embedded-rom")]
+const EMBEDDED_ROM: Option<&[u8]> = Some(include_bytes!(env!("CHIP8_EMBEDDED_ROM")));
+#[cfg(not(feature = "embedded-rom"))]
+const EMBEDDED_ROM: Option<&[u8]> = None;
+
+const CHECK_FRAMES: u64 = 120;
+const CHECK_SEED: u64 = 0x5EED;
+const PERF_INTERVAL: Duration = Duration::from_millis(500);
+
+// (quirk, flag enabling it, flag disabling it)
+const QUIRK_FLAGS: [(&str, &str, &str); 11] = [
+    ("shift_vy", "shift-vy", "no-shift-vy"),
+    ("wrap_x", "wrap-x", "no-wrap-x"),
+    ("wrap_y", "wrap-y", "no-wrap-y"),
+    ("vf_reset", "vf-reset", "no-vf-reset"),
+    ("increment_i", "increment-i", "no-increment-i"),
+    ("jump_vx", "jump-vx", "no-jump-vx"),
+    ("wrap_i", "wrap-i", "no-wrap-i"),
+    ("fx1e_sets_vf", "fx1e-sets-vf", "no-fx1e-sets-vf"),
+    ("display_wait", "display-wait", "no-display-wait"),
+    ("cls_waits", "cls-waits", "no-cls-waits"),
+    ("cls_resets_vf", "cls-resets-vf", "no-cls-resets-vf"),
+];
+
+// Keys laid out like chip8::KEYPAD_LAYOUT on a qwerty keyboard
+const KEYBOARD_LAYOUT: [[Keycode; 4]; 4] = [
+    [Keycode::Num1, Keycode::Num2, Keycode::Num3, Keycode::Num4],
+    [Keycode::Q, Keycode::W, Keycode::E, Keycode::R],
+    [Keycode::A, Keycode::S, Keycode::D, Keycode::F],
+    [Keycode::Z, Keycode::X, Keycode::C, Keycode::V],
+];
+
+fn keypad_mask(keycode: Keycode) -> u16 {
+    for (row, keys) in KEYBOARD_LAYOUT.iter().enumerate() {
+        if let Some(col) = keys.iter().position(|&key| key == keycode) {
+            return 1 << chip8::KEYPAD_LAYOUT[row][col];
+        }
+    }
+    0
+}
+
+fn quirk_mut<'a>(quirks: &'a mut Quirks, name: &str) -> &'a mut bool {
+    match name {
+        "shift_vy" => &mut quirks.shift_vy,
+        "wrap_x" => &mut quirks.wrap_x,
+        "wrap_y" => &mut quirks.wrap_y,
+        "vf_reset" => &mut quirks.vf_reset,
+        "increment_i" => &mut quirks.increment_i,
+        "jump_vx" => &mut quirks.jump_vx,
+        "wrap_i" => &mut quirks.wrap_i,
+        "fx1e_sets_vf" => &mut quirks.fx1e_sets_vf,
+        "display_wait" => &mut quirks.display_wait,
+        "cls_waits" => &mut quirks.cls_waits,
+        "cls_resets_vf" => &mut quirks.cls_resets_vf,
+        _ => unreachable!(),
+    }
+}
+
+fn draw_beep_border(canvas: &mut Canvas<Window>) -> Result<(), String> {
+    let (width, height) = canvas.output_size()?;
+    canvas.set_draw_color(Color::RGB(255, 64, 64));
+    canvas.fill_rects(&[
+        Rect::new(0, 0, width, BEEP_BORDER),
+        Rect::new(0, (height - BEEP_BORDER) as i32, width, BEEP_BORDER),
+        Rect::new(0, 0, BEEP_BORDER, height),
+        Rect::new((width - BEEP_BORDER) as i32, 0, BEEP_BORDER, height),
+    ])?;
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    Ok(())
+}
+
+// Darkens every other row of the display and shades its edges
+fn draw_crt(canvas: &mut Canvas<Window>, rect: Rect) -> Result<(), String> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, SCANLINE_ALPHA));
+    let scanlines: Vec<Rect> = (rect.y()..rect.bottom())
+        .step_by(2)
+        .map(|y| Rect::new(rect.x(), y, rect.width(), 1))
+        .collect();
+    canvas.fill_rects(&scanlines)?;
+    for step in 0..VIGNETTE_STEPS {
+        let inset = (step * VIGNETTE_WIDTH) as i32;
+        let width = rect.width() - 2 * step * VIGNETTE_WIDTH;
+        let height = rect.height() - 2 * step * VIGNETTE_WIDTH;
+        let alpha = (VIGNETTE_ALPHA as u32 * (VIGNETTE_STEPS - step) / VIGNETTE_STEPS) as u8;
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, alpha));
+        canvas.fill_rects(&[
+            Rect::new(rect.x() + inset, rect.y() + inset, width, VIGNETTE_WIDTH),
+            Rect::new(rect.x() + inset, rect.bottom() - inset - VIGNETTE_WIDTH as i32, width, VIGNETTE_WIDTH),
+            Rect::new(rect.x() + inset, rect.y() + inset, VIGNETTE_WIDTH, height),
+            Rect::new(rect.right() - inset - VIGNETTE_WIDTH as i32, rect.y() + inset, VIGNETTE_WIDTH, height),
+        ])?;
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    Ok(())
+}
+
+// Undoes --rotate and --flip-h/--flip-v on window coordinates, giving
+// coordinates relative to the unrotated scaled display
+fn untransform(x: i32, y: i32, display_rect: Rect, rotate: u32, flip_h: bool, flip_v: bool) -> (i32, i32) {
+    let (x, y) = (x - display_rect.x(), y - display_rect.y());
+    let (width, height) = (display_rect.width() as i32, display_rect.height() as i32);
+    let (x, y, width, height) = match rotate {
+        90 => (y, width - 1 - x, height, width),
+        180 => (width - 1 - x, height - 1 - y, width, height),
+        270 => (height - 1 - y, x, height, width),
+        _ => (x, y, width, height),
+    };
+    let x = if flip_h { width - 1 - x } else { x };
+    let y = if flip_v { height - 1 - y } else { y };
+    (x, y)
+}
+
+// Prints one line per rom, returns whether all of them passed
+fn check_roms(dir: &str, config: Chip8Config) -> Result<bool, FrontError> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ch8")));
+    paths.sort();
+    let mut failures = 0;
+    for path in &paths {
+        let result = fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|rom| {
+                let mut chip8 = Chip8::new_with_config(config);
+                let unknown = chip8::verify_rom(&rom, chip8.capacity())?;
+                chip8.load_rom(&rom)?;
+                chip8.run_frames(CHECK_FRAMES)?;
+                Ok(unknown)
+            });
+        match result {
+            Ok(unknown) if unknown.is_empty() => println!("pass {}", path.display()),
+            Ok(unknown) => println!(
+                "pass {} ({} words that do not decode, likely data)",
+                path.display(),
+                unknown.len(),
+            ),
+            Err(err) => {
+                failures += 1;
+                println!("FAIL {}: {}", path.display(), err);
+            }
+        }
+    }
+    println!("{} of {} roms passed", paths.len() - failures, paths.len());
+    Ok(failures == 0)
+}
+
+fn parse_addr(addr: &str) -> Result<u16, String> {
+    let digits = addr.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|e| format!("invalid address {}: {}", addr, e))
+}
+
+fn parse_poke(poke: &str) -> Result<(u16, u8), String> {
+    let (addr, value) = poke.split_once('=')
+        .ok_or_else(|| format!("invalid poke {}, expected ADDR=VAL", poke))?;
+    let value = value.trim_start_matches("0x").trim_start_matches("0X");
+    let value = u8::from_str_radix(value, 16)
+        .map_err(|e| format!("invalid poke value {}: {}", value, e))?;
+    Ok((parse_addr(addr)?, value))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Watch {
+    Register(usize),
+    I,
+    Pc,
+    DelayTimer,
+    SoundTimer,
+    Memory(u16),
+}
+
+impl Watch {
+    fn show(self, chip8: &Chip8) -> String {
+        match self {
+            Watch::Register(idx) => format!("V{:X}={:02X}", idx, chip8.register(idx).unwrap_or(0)),
+            Watch::I => format!("I={:03X}", chip8.i()),
+            Watch::Pc => format!("PC={:03X}", chip8.pc()),
+            Watch::DelayTimer => format!("DT={:02X}", chip8.delay_timer()),
+            Watch::SoundTimer => format!("ST={:02X}", chip8.sound_timer()),
+            Watch::Memory(addr) => format!("[{:03X}]={:02X}", addr, chip8.memory[addr as usize]),
+        }
+    }
+}
+
+// Addresses must fall inside memory_size, which depends on XO-CHIP mode
+fn parse_watch(list: &str, memory_size: usize) -> Result<Vec<Watch>, String> {
+    list.split(',')
+        .map(|name| match name.trim().to_ascii_uppercase().as_str() {
+            "I" => Ok(Watch::I),
+            "PC" => Ok(Watch::Pc),
+            "DT" => Ok(Watch::DelayTimer),
+            "ST" => Ok(Watch::SoundTimer),
+            register if register.len() == 2 && register.starts_with('V') => {
+                usize::from_str_radix(&register[1..], 16)
+                    .map(Watch::Register)
+                    .map_err(|_| format!("invalid register {}", name))
+            }
+            _ => {
+                let addr = parse_addr(name.trim())?;
+                if addr as usize >= memory_size {
+                    return Err(format!("address {} out of memory", name));
+                }
+                Ok(Watch::Memory(addr))
+            }
+        })
+        .collect()
+}
+
+fn read_program(path: &str, stdin_rom: &[u8]) -> io::Result<Vec<u8>> {
+    let rom = if path == "-" {
+        stdin_rom.to_vec()
+    } else {
+        fs::read(path)?
+    };
+    println!("playing {}", path);
+    Ok(rom)
+}
+
+// Loads the rom's saved RPL flags, zeroed for a rom that never saved any,
+// and writes them back each time the rom saves
+fn restore_flags(chip8: &mut Chip8, dir: &str, rom: &[u8]) {
+    let dir = Path::new(dir).to_path_buf();
+    let path = dir.join(format!("{:016x}.flags", chip8::rom_hash(rom)));
+    chip8.set_rpl_flags(&fs::read(&path).unwrap_or_default());
+    chip8.set_flags_hook(Box::new(move |flags| {
+        if let Err(err) = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, flags)) {
+            eprintln!("could not save flags to {}: {}", path.display(), err);
+        }
+    }));
+}
+
+// A matching profile replaces the quirks, XO-CHIP mode and speed from the
+// command line. Called before the rom is loaded, XO-CHIP mode decides how
+// much of it fits
+fn apply_profile(
+    chip8: &mut Chip8,
+    profiles: &Profiles,
+    path: &str,
+    rom: &[u8],
+    quirks: Quirks,
+    xochip: bool,
+) {
+    let profile = profiles.find(path, rom).copied().unwrap_or_default();
+    chip8.quirks = profile.quirks.unwrap_or(quirks);
+    chip8.set_xochip(profile.xochip.unwrap_or(xochip));
+    chip8.set_cycles_per_frame(profile.cycles_per_frame
+        .unwrap_or(Chip8Config::default().cycles_per_frame));
+    if profile.quirks.is_some() || profile.cycles_per_frame.is_some() {
+        println!("profile: {:?}", profile);
+    }
+}
+
+fn main() -> Result<(), FrontError> {
+    let program_path = Arg::new("program_path")
+        .multiple_values(true)
+        .help("Paths to the roms to run, or - to read one from stdin. \
+            PageUp/PageDown switch between roms. A file picker opens without one");
+    let mut command = Command::new("chip8")
+        .arg(program_path)
+        .arg(Arg::new("check")
+            .long("check")
+            .takes_value(true)
+            .conflicts_with("program_path")
+            .help("Run every .ch8 rom in the given directory for a few frames without \
+                opening a window, and report which ones fail"))
+        .arg(Arg::new("volume")
+            .long("volume")
+            .takes_value(true)
+            .default_value("0.25")
+            .help("Beep volume, from 0.0 to 1.0"))
+        .arg(Arg::new("beep_hz")
+            .long("beep-hz")
+            .takes_value(true)
+            .help("Beep frequency in Hz, 440 by default"))
+        .arg(Arg::new("waveform")
+            .long("waveform")
+            .takes_value(true)
+            .possible_values(["square", "sine", "triangle"])
+            .default_value("square")
+            .help("Shape of the beep"))
+        .arg(Arg::new("quirks")
+            .long("quirks")
+            .takes_value(true)
+            .possible_values(["chip8", "schip", "xochip"])
+            .help("Compatibility profile, individual quirks can be overridden. \
+                xochip also turns on 64K memory, F000 and display planes"))
+        .arg(Arg::new("fade")
+            .long("fade")
+            .help("Fade unlit pixels out over a few frames to reduce flicker, toggled with F1"))
+        .arg(Arg::new("fade_decay")
+            .long("fade-decay")
+            .takes_value(true)
+            .default_value("0.6")
+            .help("Fraction of a pixel's intensity kept each frame once unlit"))
+        .arg(Arg::new("visual_beep")
+            .long("visual-beep")
+            .help("Flash a border around the screen while the beep is playing"))
+        .arg(Arg::new("crt")
+            .long("crt")
+            .help("Overlay scanlines and a vignette for an old screen look"))
+        .arg(Arg::new("border")
+            .long("border")
+            .takes_value(true)
+            .default_value("0")
+            .help("Width in display pixels of an empty margin around the display"))
+        .arg(Arg::new("flip_h")
+            .long("flip-h")
+            .help("Mirror the display horizontally"))
+        .arg(Arg::new("flip_v")
+            .long("flip-v")
+            .help("Mirror the display vertically"))
+        .arg(Arg::new("rotate")
+            .long("rotate")
+            .takes_value(true)
+            .possible_values(["0", "90", "180", "270"])
+            .default_value("0")
+            .help("Rotate the display clockwise by this many degrees"))
+        .arg(Arg::new("step")
+            .long("step")
+            .help("Start paused in the debugger at the first instruction, F6 steps, F7 counts the timers down and F5 resumes"))
+        .arg(Arg::new("pause_on_blur")
+            .long("pause-on-blur")
+            .help("Pause emulation and sound while the window is unfocused"))
+        .arg(Arg::new("info")
+            .long("info")
+            .help("Print the machine settings for the rom, profile applied, and exit"))
+        .arg(Arg::new("perf")
+            .long("perf")
+            .help("Show frames and instructions per second in the window title"))
+        .arg(Arg::new("watch")
+            .long("watch")
+            .takes_value(true)
+            .validator(|list| parse_watch(list, chip8::XO_MEMORY_SIZE))
+            .help("Show values in the window title, a list of V0-VF, I, PC, DT, ST or \
+                memory addresses, e.g. V0,V3,I,0x2F0"))
+        .arg(Arg::new("keypad")
+            .long("keypad")
+            .help("Show a clickable hex keypad below the display"))
+        .arg(Arg::new("trace")
+            .long("trace")
+            .takes_value(true)
+            .min_values(0)
+            .require_equals(true)
+            .help("Log each executed instruction to the given file, or stderr with no file"))
+        .arg(Arg::new("trace_from")
+            .long("trace-from")
+            .takes_value(true)
+            .validator(parse_addr)
+            .help("Start the trace the first time this address is executed, e.g. 0x2A0"))
+        .arg(Arg::new("trace_count")
+            .long("trace-count")
+            .takes_value(true)
+            .help("Stop the trace after this many instructions"))
+        .arg(Arg::new("poke")
+            .long("poke")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .validator(parse_poke)
+            .help("Write a byte to memory every frame, as hex ADDR=VAL, e.g. 0x2F0=03"))
+        .arg(Arg::new("ipf")
+            .long("ipf")
+            .takes_value(true)
+            .help("Run this many instructions per frame whatever they cost, e.g. 11, \
+                instead of a cycle budget"))
+        .arg(Arg::new("flags_dir")
+            .long("flags-dir")
+            .takes_value(true)
+            .default_value("flags")
+            .help("Where the RPL flags roms use as save data are kept, one file per rom. \
+                Not used while recording or replaying, so sessions replay the same"))
+        .arg(Arg::new("profiles")
+            .long("profiles")
+            .takes_value(true)
+            .default_value("profiles.toml")
+            .help("Per-game quirks and speed, keyed by rom hash or file name"))
+        .arg(Arg::new("record")
+            .long("record")
+            .takes_value(true)
+            .help("Write the seed, settings and keypad input to this file on exit, \
+                for --replay. Turbo is disabled while recording"))
+        .arg(Arg::new("replay")
+            .long("replay")
+            .takes_value(true)
+            .help("Replay a session written by --record or left in chip8-crash.log, \
+                pausing in the debugger where it ended"))
+        .arg(Arg::new("invalid_ops")
+            .long("invalid-ops")
+            .takes_value(true)
+            .possible_values(["error", "skip", "halt"])
+            .default_value("error")
+            .help("What to do when an invalid opcode is executed"));
+    for (_, on, off) in QUIRK_FLAGS {
+        command = command
+            .arg(Arg::new(on).long(on).overrides_with(off))
+            .arg(Arg::new(off).long(off).overrides_with(on));
+    }
+    let matches = command.get_matches();
+    let volume = matches.value_of_t::<f32>("volume")
+        .unwrap_or_else(|e| e.exit())
+        .clamp(0.0, 1.0);
+    let beep_hz = matches.value_of("beep_hz")
+        .map(|_| matches.value_of_t::<u16>("beep_hz").unwrap_or_else(|e| e.exit()));
+    let waveform = match matches.value_of("waveform") {
+        Some("sine") => Waveform::Sine,
+        Some("triangle") => Waveform::Triangle,
+        _ => Waveform::Square,
+    };
+    let mut quirks = matches.value_of("quirks")
+        .and_then(Quirks::from_name)
+        .unwrap_or_default();
+    let xochip = matches.value_of("quirks") == Some("xochip");
+    for (name, on, off) in QUIRK_FLAGS {
+        if matches.is_present(on) {
+            *quirk_mut(&mut quirks, name) = true;
+        } else if matches.is_present(off) {
+            *quirk_mut(&mut quirks, name) = false;
+        }
+    }
+    println!("quirks: {:?}", quirks);
+    let mut fade = matches.is_present("fade");
+    let fade_decay = matches.value_of_t::<f32>("fade_decay")
+        .unwrap_or_else(|e| e.exit())
+        .clamp(0.0, 1.0);
+    let visual_beep = matches.is_present("visual_beep");
+    let crt = matches.is_present("crt");
+    let border = matches.value_of_t::<u32>("border").unwrap_or_else(|e| e.exit()) * DISPLAY_SCALE;
+    let flip_h = matches.is_present("flip_h");
+    let flip_v = matches.is_present("flip_v");
+    let rotate = matches.value_of_t::<u32>("rotate").unwrap_or_else(|e| e.exit());
+    let show_keypad = matches.is_present("keypad");
+    let pause_on_blur = matches.is_present("pause_on_blur");
+    let show_perf = matches.is_present("perf");
+    let invalid_op_policy = match matches.value_of("invalid_ops") {
+        Some("skip") => InvalidOpPolicy::SkipAsNop,
+        Some("halt") => InvalidOpPolicy::Halt,
+        _ => InvalidOpPolicy::Error,
+    };
+
+    let instructions_per_frame = matches.value_of("ipf")
+        .map(|_| matches.value_of_t::<usize>("ipf").unwrap_or_else(|e| e.exit()));
+    let profiles = Profiles::load(matches.value_of("profiles").unwrap())?;
+
+    let trace = if matches.is_present("trace") {
+        let out: Box<dyn Write> = match matches.value_of("trace") {
+            Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
+            None => Box::new(io::stderr()),
+        };
+        let from = matches.value_of("trace_from").map(|addr| parse_addr(addr).unwrap());
+        let count = matches.value_of("trace_count")
+            .map(|_| matches.value_of_t::<u64>("trace_count").unwrap_or_else(|e| e.exit()));
+        Some((out, from, count))
+    } else {
+        None
+    };
+
+    if let Some(dir) = matches.value_of("check") {
+        let config = Chip8Config {
+            quirks,
+            invalid_op_policy,
+            instructions_per_frame,
+            xochip,
+            seed: Some(CHECK_SEED),
+            ..Chip8Config::default()
+        };
+        if !check_roms(dir, config)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let record_path = matches.value_of("record");
+    let replay = matches.value_of("replay").map(Session::read).transpose()?;
+    let seed = match &replay {
+        Some(session) => session.seed,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    };
+    let mut chip8 = Chip8::new_with_config(Chip8Config {
+        quirks,
+        invalid_op_policy,
+        instructions_per_frame,
+        xochip,
+        tone_hz: beep_hz,
+        seed: Some(seed),
+        ..Chip8Config::default()
+    });
+    chip8.start_recording();
+    for poke in matches.values_of("poke").into_iter().flatten() {
+        let (addr, value) = parse_poke(poke).unwrap();
+        chip8.add_poke(addr, value);
+    }
+    chip8.set_halt_hook(Box::new(|| println!("program exited")));
+    if let Some((mut out, from, mut remaining)) = trace {
+        let mut started = from.is_none();
+        chip8.set_trace_hook(Box::new(move |pc, op0, op1| {
+            started |= from == Some(pc);
+            if !started || remaining == Some(0) {
+                return;
+            }
+            remaining = remaining.map(|count| count - 1);
+            // A broken trace file is not worth stopping the emulation for
+            let _ = writeln!(out, "{:03X}: {}", pc, chip8::disassemble(op0, op1));
+        }));
+    }
+    // Nothing to run, e.g. started by double-clicking, so ask for a rom
+    let picked = if matches.values_of("program_path").is_none() && EMBEDDED_ROM.is_none() {
+        let path = rfd::FileDialog::new()
+            .set_title("Open a CHIP-8 rom")
+            .add_filter("CHIP-8 roms", &["ch8"])
+            .add_filter("All files", &["*"])
+            .pick_file();
+        match path {
+            Some(path) => Some(path.to_string_lossy().into_owned()),
+            None => return Ok(()),
+        }
+    } else {
+        None
+    };
+    let program_paths: Vec<&str> = match &picked {
+        Some(path) => vec![path.as_str()],
+        None => matches.values_of("program_path").map_or_else(Vec::new, |paths| paths.collect()),
+    };
+    // stdin can only be read once, keep it around for when the playlist wraps
+    let mut stdin_rom = Vec::new();
+    if program_paths.contains(&"-") {
+        io::stdin().read_to_end(&mut stdin_rom)?;
+    }
+    let mut program_idx = 0;
+    // Kept around so a reset does not have to read the rom again
+    let mut rom = match program_paths.first() {
+        Some(path) => read_program(path, &stdin_rom)?,
+        None => {
+            println!("playing embedded rom");
+            EMBEDDED_ROM.expect("a rom is picked without an embedded rom").to_vec()
+        }
+    };
+    apply_profile(&mut chip8, &profiles, program_paths.first().unwrap_or(&""), &rom, quirks, xochip);
+    // XO-CHIP mode decides how much of the rom fits, so a replay sets it first
+    if let Some(session) = &replay {
+        chip8.set_xochip(session.xochip);
+    }
+    chip8.load_rom(&rom)?;
+    let flags_dir = matches.value_of("flags_dir").filter(|_| record_path.is_none() && replay.is_none());
+    if let Some(dir) = flags_dir {
+        restore_flags(&mut chip8, dir, &rom);
+    }
+    let mut replay_inputs = match &replay {
+        Some(session) => {
+            if session.rom_hash != chip8::rom_hash(&rom) {
+                return Err(FrontError::Chip8("the session was recorded with another rom".to_string()));
+            }
+            chip8.quirks = session.quirks;
+            chip8.set_cycles_per_frame(session.cycles_per_frame);
+            chip8.set_instructions_per_frame(session.instructions_per_frame);
+            println!("replaying {} frames", session.frames);
+            Some(session.inputs.iter().copied().peekable())
+        }
+        None => None,
+    };
+    let mut replay_keypad = 0;
+    let watches = match matches.value_of("watch") {
+        Some(list) => parse_watch(list, chip8.memory_size()).map_err(FrontError::Chip8)?,
+        None => vec![],
+    };
+    if matches.is_present("info") {
+        println!("{:#?}", chip8.info());
+        return Ok(());
+    }
+
+    let sdl_context = sdl2::init().map_err(|e| FrontError::Sdl(format!("could not initialize SDL: {}", e)))?;
+
+    let desired_spec = AudioSpecDesired {
+        freq: Some(44100),
+        channels: Some(1), // mono
+        samples: None,     // default sample size
+    };
+
+    // Plenty of machines have no audio device, run silent rather than not at all
+    let device = sdl_context.audio().and_then(|audio_subsystem| {
+        audio_subsystem.open_playback(None, &desired_spec, |spec| {
+            // initialize the audio callback
+            let mut beep = Beep { waveform, pattern: None, phase_inc: 0.0, phase: 0.0, volume };
+            beep.update(&chip8, spec.freq);
+            beep
+        })
+    });
+    let mut device = match device {
+        Ok(device) => Some(device),
+        Err(err) => {
+            eprintln!("warning: no audio, running without sound: {}", err);
+            None
+        }
+    };
+
+    let video_subsystem = sdl_context.video().map_err(|e| {
+        FrontError::Sdl(format!("could not initialize video, a display is required: {}", e))
+    })?;
+    let (display_width, display_height) = (
+        chip8::DISPLAY_WIDTH as u32 * DISPLAY_SCALE,
+        chip8::DISPLAY_HEIGHT as u32 * DISPLAY_SCALE,
+    );
+    // A quarter turn swaps the on-screen width and height. copy_ex rotates
+    // around the destination's center, so the texture keeps its own size
+    // centered on the display
+    let sideways = rotate % 180 == 90;
+    let display_rect = if sideways {
+        Rect::new(border as i32, border as i32, display_height, display_width)
+    } else {
+        Rect::new(border as i32, border as i32, display_width, display_height)
+    };
+    let texture_rect = Rect::from_center(display_rect.center(), display_width, display_height);
+    let window_width = display_rect.width() + 2 * border;
+    let screen_height = display_rect.height() + 2 * border;
+    let touch_keypad = show_keypad
+        .then(|| keypad::TouchKeypad::new(window_width, screen_height));
+    let keypad_height = if show_keypad { keypad::STRIP_HEIGHT } else { 0 };
+    let window = video_subsystem
+        .window(
+            "chip8",
+            window_width,
+            screen_height + keypad_height,
+        )
+        .position_centered()
+        .opengl()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+    canvas.present();
+
+    let texture_creator = canvas.texture_creator();
+    // Always hires, a lores pixel covers 2x2 texels
+    let mut tex_display = texture_creator
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            chip8::HIRES_WIDTH as u32,
+            chip8::HIRES_HEIGHT as u32,
+        )
+        .map_err(|e| e.to_string())?;
+
+    let frame_duration = Duration::new(0, 1_000_000_000u32 / 60);
+    let mut timestamp = Instant::now();
+
+    let mut event_pump = sdl_context.event_pump()?;
+
+    let mut keypad: u16 = 0u16;
+    let mut mouse_key: Option<u8> = None;
+    let mut intensities = [0f32; chip8::HIRES_WIDTH * chip8::HIRES_HEIGHT];
+    let mut debug = matches.is_present("step");
+    let mut blurred = false;
+    let mut turbo = false;
+    let mut perf_timestamp = Instant::now();
+    let mut perf_frames = 0u32;
+    let mut perf_instructions = chip8.instruction_count();
+
+    'main: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => {
+                    break 'main;
+                },
+                Event::Window { win_event: WindowEvent::FocusLost, .. } if pause_on_blur => {
+                    blurred = true;
+                }
+                Event::Window { win_event: WindowEvent::FocusGained, .. } => {
+                    blurred = false;
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    mouse_key = touch_keypad.as_ref().and_then(|touch| touch.key_at(x, y));
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Right,
+                    x,
+                    y,
+                    ..
+                } => {
+                    let (x, y) = untransform(x, y, display_rect, rotate, flip_h, flip_v);
+                    let width = chip8.display_width();
+                    let scale = DISPLAY_SCALE * chip8::DISPLAY_WIDTH as u32 / width as u32;
+                    let size = (width, chip8.display_height());
+                    if let Some((px, py)) = chip8::window_to_pixel(x, y, 0, 0, scale, size) {
+                        let idx = chip8::pixel_byte_index(px, py, width);
+                        println!(
+                            "pixel ({}, {}) {}, display byte {} = {:08b}",
+                            px,
+                            py,
+                            if chip8.pixel(px, py) { "on" } else { "off" },
+                            idx,
+                            chip8.display[idx],
+                        );
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } => {
+                    mouse_key = None;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::R),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    chip8.reset();
+                    // The input log starts over, so does the rng for it to replay
+                    chip8.seed_rng(seed);
+                    chip8.load_rom(&rom)?;
+                    println!("reset");
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat,
+                    ..
+                } => {
+                    keypad |= keypad_mask(keycode);
+                    // Released before the next frame it would be missed
+                    // otherwise. A replay has its keypad recorded already
+                    let key = chip8::Key::from_index(keypad_mask(keycode).trailing_zeros() as u8);
+                    if !repeat && replay_inputs.is_none() {
+                        if let Some(key) = key {
+                            chip8.queue_key_press(key);
+                        }
+                    }
+                    match keycode {
+                        Keycode::Minus => {
+                            if let Some(device) = &mut device {
+                                let mut wave = device.lock();
+                                wave.volume = (wave.volume - VOLUME_STEP).clamp(0.0, 1.0);
+                            }
+                        }
+                        Keycode::Equals => {
+                            if let Some(device) = &mut device {
+                                let mut wave = device.lock();
+                                wave.volume = (wave.volume + VOLUME_STEP).clamp(0.0, 1.0);
+                            }
+                        }
+                        Keycode::F1 => {
+                            fade = !fade;
+                        }
+                        // Fast frames skip the timers, a replay would not know which ones were
+                        Keycode::Tab if record_path.is_none() && replay.is_none() => {
+                            turbo = true;
+                        }
+                        Keycode::F5 => {
+                            debug = !debug;
+                        }
+                        Keycode::F6 if debug => {
+                            chip8.tick()?;
+                        }
+                        Keycode::F7 if debug => {
+                            chip8.decrement_timers();
+                        }
+                        Keycode::PageUp | Keycode::PageDown if program_paths.len() > 1 => {
+                            program_idx = if keycode == Keycode::PageDown {
+                                (program_idx + 1) % program_paths.len()
+                            } else {
+                                (program_idx + program_paths.len() - 1) % program_paths.len()
+                            };
+                            rom = read_program(program_paths[program_idx], &stdin_rom)?;
+                            let path = program_paths[program_idx];
+                            apply_profile(&mut chip8, &profiles, path, &rom, quirks, xochip);
+                            chip8.reset();
+                            chip8.seed_rng(seed);
+                            chip8.load_rom(&rom)?;
+                            if let Some(dir) = flags_dir {
+                                restore_flags(&mut chip8, dir, &rom);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    keypad &= !keypad_mask(keycode);
+                    if keycode == Keycode::Tab {
+                        turbo = false;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let running = !debug && !blurred;
+        if let Some(device) = &mut device {
+            if chip8.is_beeping() && running {
+                // Roms change the pattern and pitch mid-beep to play music
+                let freq = device.spec().freq;
+                device.lock().update(&chip8, freq);
+                if device.status() != AudioStatus::Playing {
+                    device.resume();
+                }
+            } else {
+                device.pause();
+            }
+        }
+        match &mut replay_inputs {
+            Some(inputs) => {
+                let frame_count = chip8.frame_count();
+                while let Some((_, keys)) = inputs.next_if(|&(frame, _)| frame <= frame_count) {
+                    replay_keypad = keys;
+                }
+                chip8.set_keypad(replay_keypad);
+            }
+            None => chip8.set_keypad(keypad | mouse_key.map_or(0, |key| 1 << key)),
+        }
+
+        if running {
+            let frames = if turbo { TURBO_FRAMES } else { 1 };
+            // Extra turbo frames are pointless while blocked on a key
+            let result = chip8.frame().and_then(|mut outcome| {
+                for _ in 1..frames {
+                    if outcome != FrameOutcome::Completed {
+                        break;
+                    }
+                    outcome = chip8.fast_frame()?;
+                }
+                Ok(())
+            });
+            for diagnostic in chip8.drain_diagnostics() {
+                eprintln!("{}", diagnostic);
+            }
+            if let Err(err) = result {
+                Session::capture(&chip8, &rom, seed).write(CRASH_LOG_PATH)?;
+                eprintln!("session written to {}, run it again with --replay", CRASH_LOG_PATH);
+                return Err(err.into());
+            }
+            if replay.as_ref().is_some_and(|session| chip8.frame_count() == session.frames) {
+                println!("replay finished, paused in the debugger");
+                replay_inputs = None;
+                debug = true;
+            }
+        }
+
+        tex_display.with_lock(None, |buffer: &mut [u8], _pitch: usize| {
+            let texel = chip8::HIRES_WIDTH / chip8.display_width();
+            for y in 0..chip8::HIRES_HEIGHT {
+                for x in 0..chip8::HIRES_WIDTH {
+                    let level = COLOR_INTENSITIES[chip8.pixel_color(x / texel, y / texel) as usize];
+                    let pixel_idx = y * chip8::HIRES_WIDTH + x;
+                    let buffer_idx = pixel_idx * 3;
+                    let intensity = &mut intensities[pixel_idx];
+                    *intensity = if level > 0.0 {
+                        level
+                    } else if fade {
+                        *intensity * fade_decay
+                    } else {
+                        0.0
+                    };
+                    let color = (*intensity * 255.0) as u8;
+                    buffer[buffer_idx] = color;
+                    buffer[buffer_idx + 1] = color;
+                    buffer[buffer_idx + 2] = color;
+                }
+            }
+        })?;
+
+        canvas.clear();
+        canvas.copy_ex(&tex_display, None, texture_rect, rotate as f64, None, flip_h, flip_v)?;
+        if crt {
+            draw_crt(&mut canvas, display_rect)?;
+        }
+        if let Some(touch) = &touch_keypad {
+            let pressed: Vec<chip8::Key> = chip8.pressed_keys().collect();
+            touch.draw(&mut canvas, &pressed)?;
+        }
+        if visual_beep && chip8.is_beeping() {
+            draw_beep_border(&mut canvas)?;
+        }
+        if debug {
+            debug::draw_overlay(&mut canvas, &chip8)?;
+        }
+        canvas.present();
+
+        perf_frames += 1;
+        let perf_elapsed = perf_timestamp.elapsed();
+        if (show_perf || !watches.is_empty()) && perf_elapsed >= PERF_INTERVAL {
+            let mut title = "chip8".to_string();
+            if show_perf {
+                let seconds = perf_elapsed.as_secs_f64();
+                let instructions = chip8.instruction_count() - perf_instructions;
+                title.push_str(&format!(
+                    " - {:.0} fps, {:.0} ips",
+                    perf_frames as f64 / seconds,
+                    instructions as f64 / seconds,
+                ));
+            }
+            if !watches.is_empty() {
+                let values: Vec<String> = watches.iter().map(|watch| watch.show(&chip8)).collect();
+                title.push_str(" - ");
+                title.push_str(&values.join(" "));
+            }
+            canvas.window_mut().set_title(&title).map_err(|e| e.to_string())?;
+            perf_timestamp = Instant::now();
+            perf_frames = 0;
+            perf_instructions = chip8.instruction_count();
+        }
+
+        let now = Instant::now();
+        let sleep_dur = frame_duration
+            .checked_sub(now.saturating_duration_since(timestamp))
+            .unwrap_or(Duration::new(0, 0));
+        std::thread::sleep(sleep_dur);
+        timestamp = now;
+    }
+
+    if let Some(path) = record_path {
+        Session::capture(&chip8, &rom, seed).write(path)?;
+        println!("session written to {}", path);
+    }
+    Ok(())
 }
\ No newline at end of file
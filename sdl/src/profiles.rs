@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use chip8::Quirks;
+
+// Per-game settings, missing entries fall back to the command line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Profile {
+    pub quirks: Option<Quirks>,
+    // Whether the quirks preset was xochip, which also turns on XO-CHIP mode
+    pub xochip: Option<bool>,
+    pub cycles_per_frame: Option<usize>,
+}
+
+// One table per game in profiles.toml, keyed by its rom_hash in hex or by
+// its file name:
+//
+//     ["9a2b3c4d5e6f7081"]
+//     quirks = "schip"
+//     cycles_per_frame = 1000
+//
+//     ["INVADERS.ch8"]
+//     quirks = "chip8"
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    quirks: Option<String>,
+    cycles_per_frame: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiles {
+    profiles: HashMap<String, Profile>,
+}
+
+impl Profiles {
+    // A missing file is not an error, there are simply no profiles
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|e| format!("{}: {}", path, e)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("{}: {}", path, err)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let entries: HashMap<String, Entry> = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut profiles = HashMap::new();
+        for (key, entry) in entries {
+            let quirks = match &entry.quirks {
+                Some(preset) => Some(Quirks::from_name(preset)
+                    .ok_or_else(|| format!("{}: unknown quirks preset {}", key, preset))?),
+                None => None,
+            };
+            let xochip = entry.quirks.as_ref().map(|preset| preset == "xochip");
+            // Hashes are printed in lowercase, accept them however they were typed
+            let key = if key.len() == 16 && key.chars().all(|c| c.is_ascii_hexdigit()) {
+                key.to_ascii_lowercase()
+            } else {
+                key
+            };
+            profiles.insert(key, Profile { quirks, xochip, cycles_per_frame: entry.cycles_per_frame });
+        }
+        Ok(Self { profiles })
+    }
+
+    // Content hash first so a renamed file keeps its profile
+    pub fn find(&self, path: &str, rom: &[u8]) -> Option<&Profile> {
+        let hash = format!("{:016x}", chip8::rom_hash(rom));
+        let file_name = Path::new(path).file_name().and_then(|name| name.to_str());
+        self.profiles.get(&hash).or_else(|| self.profiles.get(file_name?))
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::io;
+
+use chip8::{Chip8, InputLog, Quirks};
+
+use crate::{quirk_mut, QUIRK_FLAGS};
+
+const HEADER: &str = "chip8 session 2";
+
+// Everything needed to replay a run: which rom it was, the rng seed, the
+// settings that change how it runs and the keypad whenever it changed.
+// Looks like this, keypad lines being the frame and the keypad bits in hex:
+//
+//     chip8 session 2
+//     rom 9a2b3c4d5e6f7081
+//     seed 1700000000000000000
+//     quirks wrap_x wrap_y
+//     xochip false
+//     cycles_per_frame 10000
+//     instructions_per_frame none
+//     frames 360
+//     12 0010
+//     20 0000
+#[derive(Debug)]
+pub struct Session {
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub xochip: bool,
+    pub cycles_per_frame: usize,
+    pub instructions_per_frame: Option<usize>,
+    // Frames run in total, replay stops there
+    pub frames: u64,
+    pub inputs: InputLog,
+}
+
+impl Session {
+    // chip8 has to have been recording since it was seeded with seed
+    pub fn capture(chip8: &Chip8, rom: &[u8], seed: u64) -> Self {
+        Self {
+            rom_hash: chip8::rom_hash(rom),
+            seed,
+            quirks: chip8.quirks,
+            xochip: chip8.is_xochip(),
+            cycles_per_frame: chip8.cycles_per_frame(),
+            instructions_per_frame: chip8.instructions_per_frame(),
+            frames: chip8.frame_count(),
+            inputs: chip8.input_log().cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let mut quirks = self.quirks;
+        let set: Vec<&str> = QUIRK_FLAGS.iter()
+            .map(|&(name, _, _)| name)
+            .filter(|name| *quirk_mut(&mut quirks, name))
+            .collect();
+        let ipf = self.instructions_per_frame.map_or("none".to_string(), |count| count.to_string());
+        let mut text = format!("{}\nrom {:016x}\nseed {}\n", HEADER, self.rom_hash, self.seed);
+        text.push_str(&format!("quirks {}\n", set.join(" ")));
+        text.push_str(&format!("xochip {}\n", self.xochip));
+        text.push_str(&format!("cycles_per_frame {}\n", self.cycles_per_frame));
+        text.push_str(&format!("instructions_per_frame {}\n", ipf));
+        text.push_str(&format!("frames {}\n", self.frames));
+        for (frame, keypad) in &self.inputs {
+            text.push_str(&format!("{} {:04x}\n", frame, keypad));
+        }
+        fs::write(path, text)
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(format!("not a session log, expected {:?} first", HEADER));
+        }
+        // The next line as "name value", value being empty for no quirks
+        let mut field = |name: &str| -> Result<String, String> {
+            let line = lines.next().unwrap_or("");
+            match line.split_once(' ') {
+                Some((key, value)) if key == name => Ok(value.trim().to_string()),
+                None if line == name => Ok(String::new()),
+                _ => Err(format!("expected a {} line", name)),
+            }
+        };
+        let rom_hash = u64::from_str_radix(&field("rom")?, 16)
+            .map_err(|e| format!("invalid rom hash: {}", e))?;
+        let seed = field("seed")?.parse().map_err(|e| format!("invalid seed: {}", e))?;
+        let mut quirks = Quirks::default();
+        for &(name, _, _) in QUIRK_FLAGS.iter() {
+            *quirk_mut(&mut quirks, name) = false;
+        }
+        for name in field("quirks")?.split_whitespace() {
+            if !QUIRK_FLAGS.iter().any(|&(quirk, _, _)| quirk == name) {
+                return Err(format!("unknown quirk {}", name));
+            }
+            *quirk_mut(&mut quirks, name) = true;
+        }
+        let xochip = field("xochip")?.parse().map_err(|e| format!("invalid xochip: {}", e))?;
+        let cycles_per_frame = field("cycles_per_frame")?.parse()
+            .map_err(|e| format!("invalid cycles_per_frame: {}", e))?;
+        let instructions_per_frame = match field("instructions_per_frame")?.as_str() {
+            "none" => None,
+            count => Some(count.parse()
+                .map_err(|e| format!("invalid instructions_per_frame: {}", e))?),
+        };
+        let frames = field("frames")?.parse().map_err(|e| format!("invalid frames: {}", e))?;
+        let mut inputs = vec![];
+        for line in lines {
+            let (frame, keypad) = line.split_once(' ')
+                .ok_or_else(|| format!("invalid keypad line {:?}", line))?;
+            let frame = frame.parse().map_err(|e| format!("invalid frame {}: {}", frame, e))?;
+            let keypad = u16::from_str_radix(keypad, 16)
+                .map_err(|e| format!("invalid keypad {}: {}", keypad, e))?;
+            inputs.push((frame, keypad));
+        }
+        Ok(Self {
+            rom_hash,
+            seed,
+            quirks,
+            xochip,
+            cycles_per_frame,
+            instructions_per_frame,
+            frames,
+            inputs,
+        })
+    }
+}